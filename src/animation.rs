@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use speedy2d::color::Color;
+use crate::diagrams::{build_diagram, DiagramStyle, LineKind};
+use crate::expr::LambdaExpr;
+use crate::graphics::{layout_scene, Scene, ScreenLabel, ScreenLine, View};
+use crate::reduction::{origin_path, StepInfo};
+
+fn ease(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_point(a: (f32, f32), b: (f32, f32), t: f32) -> (f32, f32) {
+    (lerp(a.0, b.0, t), lerp(a.1, b.1, t))
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::from_rgba(lerp(a.r(), b.r(), t), lerp(a.g(), b.g(), t), lerp(a.b(), b.b(), t), lerp(a.a(), b.a(), t))
+}
+
+fn faded(line: &ScreenLine, alpha: f32) -> ScreenLine {
+    ScreenLine {
+        start: line.start,
+        end: line.end,
+        thickness: line.thickness,
+        color: Color::from_rgba(line.color.r(), line.color.g(), line.color.b(), line.color.a() * alpha)
+    }
+}
+
+fn faded_label(label: &ScreenLabel, alpha: f32) -> ScreenLabel {
+    ScreenLabel {
+        position: label.position,
+        text: label.text.clone(),
+        size: label.size,
+        color: Color::from_rgba(label.color.r(), label.color.g(), label.color.b(), label.color.a() * alpha)
+    }
+}
+
+pub(crate) fn transition_scene(before: &LambdaExpr, after: &LambdaExpr, steps: &[StepInfo], area: (f32, f32), view: &View, t: f32, style: DiagramStyle) -> Scene {
+    let t = ease(t.clamp(0.0, 1.0));
+    let old_diagram = build_diagram(before, style);
+    let new_diagram = build_diagram(after, style);
+    let old_scene = layout_scene(&old_diagram, area, view, steps, false);
+    let new_scene = layout_scene(&new_diagram, area, view, &[], false);
+    let (old_lines, old_arrowheads) = old_scene.lines.split_at(old_diagram.lines.len());
+    let (new_lines, new_arrowheads) = new_scene.lines.split_at(new_diagram.lines.len());
+    let old_index: HashMap<(Vec<usize>, LineKind), usize> = old_diagram.lines.iter()
+        .enumerate()
+        .map(|(i, line)| ((line.source.clone(), line.kind), i))
+        .collect();
+    let mut matched = vec![false; old_lines.len()];
+    let mut lines = vec![];
+    for (line, new_line) in new_diagram.lines.iter().zip(new_lines) {
+        let origin = origin_path(before, steps, &line.source)
+            .and_then(|path| old_index.get(&(path, line.kind)).copied())
+            .filter(|i| !matched[*i]);
+        match origin {
+            Some(i) => {
+                matched[i] = true;
+                let old_line = &old_lines[i];
+                lines.push(ScreenLine {
+                    start: lerp_point(old_line.start, new_line.start, t),
+                    end: lerp_point(old_line.end, new_line.end, t),
+                    thickness: lerp(old_line.thickness, new_line.thickness, t),
+                    color: lerp_color(old_line.color, new_line.color, t)
+                });
+            }
+            None => lines.push(faded(new_line, t))
+        }
+    }
+    for (old_line, _) in old_lines.iter().zip(matched).filter(|(_, matched)| !matched) {
+        lines.push(faded(old_line, 1.0 - t));
+    }
+    lines.extend(old_arrowheads.iter().map(|line| faded(line, 1.0 - t)));
+    lines.extend(new_arrowheads.iter().map(|line| faded(line, t)));
+    let old_label_index: HashMap<&[usize], usize> = old_diagram.labels.iter()
+        .zip(&old_scene.labels)
+        .enumerate()
+        .map(|(i, (label, _))| (label.source.as_slice(), i))
+        .collect();
+    let mut labels = vec![];
+    let mut matched_labels = vec![false; old_scene.labels.len()];
+    for (label, new_label) in new_diagram.labels.iter().zip(&new_scene.labels) {
+        let origin = origin_path(before, steps, &label.source)
+            .and_then(|path| old_label_index.get(path.as_slice()).copied())
+            .filter(|i| !matched_labels[*i] && old_scene.labels[*i].text == new_label.text);
+        match origin {
+            Some(i) => {
+                matched_labels[i] = true;
+                let old_label = &old_scene.labels[i];
+                labels.push(ScreenLabel {
+                    position: lerp_point(old_label.position, new_label.position, t),
+                    text: new_label.text.clone(),
+                    size: lerp(old_label.size, new_label.size, t),
+                    color: lerp_color(old_label.color, new_label.color, t)
+                });
+            }
+            None => labels.push(faded_label(new_label, t))
+        }
+    }
+    for (old_label, _) in old_scene.labels.iter().zip(matched_labels).filter(|(_, matched)| !matched) {
+        labels.push(faded_label(old_label, 1.0 - t));
+    }
+    Scene { lines, labels }
+}
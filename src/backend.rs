@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use futures::future::BoxFuture;
+use crate::cache::CachedBackend;
+use crate::config::{Config, EnglishMode};
+use crate::decoding::parse_arithmetic;
+use crate::english::EnglishBackend;
+use crate::expr::LambdaExpr;
+use crate::ollama::OllamaBackend;
+use crate::prelude::parse_prelude_term;
+use crate::session::Session;
+use crate::structured::parse_structured;
+
+const FIXTURE_SEPARATOR: &str = " => ";
+pub(crate) const CORRECTION_PREFIX: &str = "That answer could not be read";
+
+/// What the model is asked to write: arithmetic, or a lambda term using the prelude.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Target {
+    Arithmetic,
+    Lambda
+}
+
+/// A rejected answer, sent back to the model together with the reason it was rejected.
+#[derive(Clone)]
+pub(crate) struct Correction {
+    pub(crate) answer: String,
+    pub(crate) error: String
+}
+
+impl Correction {
+    pub(crate) fn message(&self) -> String {
+        format!("{CORRECTION_PREFIX}: {}. Reply with ONLY the corrected expression", self.error)
+    }
+}
+
+pub(crate) trait TranslationBackend: Send + Sync {
+    /// Translates `prompt` into `target` following the exchanges in `session`, continuing the
+    /// conversation after each of `corrections`.
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>>;
+
+    /// Confirms the backend can answer before any query is sent.
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+pub(crate) fn normalize(prompt: &str) -> String {
+    prompt.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase()
+}
+
+/// Answers from a fixture; a prompt listed several times gives one answer per attempt.
+pub(crate) struct MockBackend {
+    responses: HashMap<String, Vec<String>>
+}
+
+impl MockBackend {
+    pub(crate) fn from_fixture(path: &Path) -> io::Result<Self> {
+        let mut responses = HashMap::new();
+        for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(FIXTURE_SEPARATOR) {
+                Some((prompt, response)) => responses.entry(normalize(prompt)).or_insert_with(Vec::new).push(response.trim().to_string()),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: expected `prompt{FIXTURE_SEPARATOR}response`", path.display(), i + 1)))
+            };
+        }
+        Ok(Self { responses })
+    }
+
+    pub(crate) fn respond(&self, prompt: &str, attempt: usize) -> Result<String, String> {
+        self.responses.get(&normalize(prompt))
+            .map(|responses| responses[attempt.min(responses.len() - 1)].clone())
+            .ok_or_else(|| format!("No scripted response for \"{}\"", prompt.trim()))
+    }
+}
+
+impl TranslationBackend for MockBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, _: Target, _: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move { self.respond(prompt, corrections.len()) })
+    }
+}
+
+/// Reads an answer as a lambda term, or for arithmetic as an expression tree when it is JSON and as text otherwise.
+pub(crate) fn parse_answer(answer: &str, target: Target, session: &Session) -> Result<LambdaExpr, String> {
+    let answer = answer.trim();
+    if target == Target::Lambda {
+        parse_prelude_term(answer, session)
+    } else if answer.starts_with('{') {
+        parse_structured(answer)
+    } else {
+        parse_arithmetic(&session.expand(answer, target)?)
+    }
+}
+
+/// Re-asks the inner backend until its answer parses, at most `retries` more times.
+struct RetryingBackend {
+    inner: Arc<dyn TranslationBackend>,
+    retries: usize
+}
+
+impl TranslationBackend for RetryingBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let mut corrections = corrections.to_vec();
+            loop {
+                let answer = self.inner.translate(prompt, target, session, &corrections).await?;
+                let error = match parse_answer(&answer, target, session) {
+                    Ok(_) => return Ok(answer),
+                    Err(e) => e
+                };
+                if corrections.len() >= self.retries {
+                    let attempts = corrections.len() + 1;
+                    return Err(format!("Could not read \"{}\": {error} (gave up after {attempts} attempt{})", answer.trim(), if attempts == 1 { "" } else { "s" }));
+                }
+                corrections.push(Correction { answer, error });
+            }
+        })
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        self.inner.check()
+    }
+}
+
+/// Asks `second` only when `first` fails to give a readable answer, warning on stderr when it does.
+struct FallbackBackend {
+    first: Arc<dyn TranslationBackend>,
+    second: Arc<dyn TranslationBackend>
+}
+
+impl TranslationBackend for FallbackBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let first = match self.first.translate(prompt, target, session, corrections).await {
+                Ok(answer) => return Ok(answer),
+                Err(e) => e
+            };
+            let answer = self.second.translate(prompt, target, session, corrections).await.map_err(|e| format!("{first}; {e}"))?;
+            eprintln!("{first}; answered by the fallback instead");
+            Ok(answer)
+        })
+    }
+
+    /// Passes when either backend can answer, warning on stderr when only `second` can. `second` is
+    /// only checked when `first` fails, since otherwise it may never be asked.
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let first = match self.first.check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => e
+            };
+            self.second.check().await.map_err(|e| format!("{first}; {e}"))?;
+            eprintln!("{first}; the fallback will answer instead");
+            Ok(())
+        })
+    }
+}
+
+/// Puts the built-in English parser before or after a model, as `mode` asks.
+fn with_english(model: Arc<dyn TranslationBackend>, mode: EnglishMode) -> Arc<dyn TranslationBackend> {
+    let english: Arc<dyn TranslationBackend> = Arc::new(RetryingBackend { inner: Arc::new(EnglishBackend), retries: 0 });
+    match mode {
+        EnglishMode::First => Arc::new(FallbackBackend { first: english, second: model }),
+        EnglishMode::Fallback => Arc::new(FallbackBackend { first: model, second: english }),
+        EnglishMode::Off => model
+    }
+}
+
+pub(crate) fn backend_from_spec(spec: &str, mut config: Config) -> Result<Arc<dyn TranslationBackend>, String> {
+    if let Some(fixture) = spec.strip_prefix("mock:") {
+        let mock = MockBackend::from_fixture(Path::new(fixture)).map_err(|e| format!("Could not load {fixture}: {e}"))?;
+        return Ok(Arc::new(RetryingBackend { inner: Arc::new(mock), retries: config.retries }));
+    }
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        let (host, port) = spec.trim_end_matches('/').rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .ok_or_else(|| format!("Expected a port in {spec}, e.g. http://127.0.0.1:11434"))?;
+        config.host = host.to_string();
+        config.port = port;
+    } else if spec != "ollama" {
+        return Err(format!("Unknown backend {spec}; expected ollama, mock:<fixture> or http://host:port"));
+    }
+    let model: Arc<dyn TranslationBackend> = Arc::new(RetryingBackend { inner: Arc::new(OllamaBackend::new(config.clone())), retries: config.retries });
+    let model: Arc<dyn TranslationBackend> = match &config.cache {
+        Some(path) => Arc::new(CachedBackend::new(model, path.clone(), &config)),
+        None => model
+    };
+    Ok(with_english(model, config.english))
+}
+
+/// Translates and parses `prompt`, returning the term together with the answer it was read from.
+pub(crate) async fn interpret_query(prompt: &str, target: Target, session: &Session, backend: &dyn TranslationBackend) -> Result<(LambdaExpr, String), String> {
+    let message = backend.translate(prompt, target, session, &[]).await?;
+    let term = parse_answer(&message, target, session).map_err(|e| format!("Could not read \"{}\": {e}", message.trim()))?;
+    Ok((term, message.trim().to_string()))
+}
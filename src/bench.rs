@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+use crate::decoding::combinator;
+use crate::expr::{ExprType, LambdaExpr};
+use crate::numerals::church;
+use crate::readback::read_back;
+use crate::reduction::{normal_form, ReductionOptions};
+
+const ITERATIONS: u32 = 3;
+
+fn apply(f: LambdaExpr, args: Vec<LambdaExpr>) -> LambdaExpr {
+    let mut expr = f;
+    for arg in args {
+        expr = LambdaExpr {
+            expr_type: ExprType::App,
+            id: 0,
+            children: vec![expr, arg],
+        };
+    }
+    expr
+}
+
+fn binary(op: &str, a: LambdaExpr, b: LambdaExpr) -> LambdaExpr {
+    apply(combinator(op).unwrap(), vec![a, b])
+}
+
+fn unary(op: &str, a: LambdaExpr) -> LambdaExpr {
+    apply(combinator(op).unwrap(), vec![a])
+}
+
+/// `λs.s a b`, whose two halves are independent redexes from the first step.
+fn pair(a: LambdaExpr, b: LambdaExpr) -> LambdaExpr {
+    let s = 's' as usize;
+    LambdaExpr {
+        expr_type: ExprType::Abs,
+        id: s,
+        children: vec![apply(LambdaExpr { expr_type: ExprType::Var, id: s, children: vec![] }, vec![a, b])],
+    }
+}
+
+fn benchmark_terms() -> Vec<(&'static str, LambdaExpr)> {
+    vec![
+        ("(20 * 20) + (20 * 20)", binary("+", binary("*", church(20), church(20)), binary("*", church(20), church(20)))),
+        ("(20 - 10) + (20 - 10)", binary("+", binary("-", church(20), church(10)), binary("-", church(20), church(10)))),
+        ("(6 / 2) + (4 / 2)", binary("+", binary("/", church(6), church(2)), binary("/", church(4), church(2)))),
+        ("(3 !) + (2 !)", binary("+", unary("!", church(3)), unary("!", church(2)))),
+        ("pair (3 !) (3 !)", pair(unary("!", church(3)), unary("!", church(3)))),
+    ]
+}
+
+fn time_reduction(expr: &LambdaExpr, options: &ReductionOptions) -> (Duration, LambdaExpr) {
+    let start = Instant::now();
+    let mut result = expr.clone();
+    for _ in 0..ITERATIONS {
+        result = normal_form(expr.clone(), options);
+    }
+    (start.elapsed() / ITERATIONS, result)
+}
+
+pub(crate) fn run_benchmark(workers: usize) {
+    let sequential = ReductionOptions::default();
+    let parallel = ReductionOptions {
+        workers,
+        ..ReductionOptions::default()
+    };
+    println!("{:<24}{:>8}{:>16}{:>16}{:>10}", "term", "value", "sequential", format!("{workers} workers"), "speedup");
+    for (name, expr) in benchmark_terms() {
+        let (sequential_time, sequential_nf) = time_reduction(&expr, &sequential);
+        let (parallel_time, parallel_nf) = time_reduction(&expr, &parallel);
+        assert_eq!(sequential_nf, parallel_nf, "Parallel reduction of {name} reached a different normal form.");
+        println!(
+            "{:<24}{:>8}{:>16}{:>16}{:>9.2}x",
+            name,
+            read_back(&sequential_nf).unwrap_or_default(),
+            format!("{:.2?}", sequential_time),
+            format!("{:.2?}", parallel_time),
+            sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use crate::backend::{normalize, Correction, Target, TranslationBackend};
+use crate::config::Config;
+use crate::ollama::instructions;
+use crate::session::Session;
+
+/// One remembered translation; `system_prompt` is a hash of everything sent before the question.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    prompt: String,
+    model: String,
+    system_prompt: String,
+    answer: String
+}
+
+/// FNV-1a, which unlike the std hasher gives the same hash on every build.
+fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("{hash:016x}")
+}
+
+fn read_entries(path: &Path) -> Result<Vec<Entry>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid translation cache {}: {e}; remove it or pass --clear-cache", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!("Could not read {}: {e}", path.display()))
+    }
+}
+
+fn write_entries(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(entries).map_err(io::Error::other)?)
+}
+
+pub(crate) fn clear_cache(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(format!("Could not clear {}: {e}", path.display())),
+        _ => Ok(())
+    }
+}
+
+/// Remembers the inner backend's readable answers in a JSON file, so a prompt asked again of the
+/// same model and instructions gets the same translation without a request.
+/// Follow-ups depend on the session and are always sent.
+pub(crate) struct CachedBackend {
+    inner: Arc<dyn TranslationBackend>,
+    path: PathBuf,
+    model: String,
+    arithmetic_prompt: String,
+    lambda_prompt: String
+}
+
+impl CachedBackend {
+    pub(crate) fn new(inner: Arc<dyn TranslationBackend>, path: PathBuf, config: &Config) -> Self {
+        let prompt_hash = |target| hash(&instructions(config, target).into_iter().map(|x| x.content).collect::<Vec<String>>().join("\n"));
+        Self {
+            inner,
+            path,
+            model: config.model.clone(),
+            arithmetic_prompt: prompt_hash(Target::Arithmetic),
+            lambda_prompt: prompt_hash(Target::Lambda)
+        }
+    }
+
+    fn system_prompt(&self, target: Target) -> &str {
+        match target {
+            Target::Arithmetic => &self.arithmetic_prompt,
+            Target::Lambda => &self.lambda_prompt
+        }
+    }
+
+    fn matches(&self, entry: &Entry, prompt: &str, target: Target) -> bool {
+        entry.prompt == normalize(prompt) && entry.model == self.model && entry.system_prompt == self.system_prompt(target)
+    }
+
+    fn store(&self, prompt: &str, target: Target, answer: &str) -> Result<(), String> {
+        let mut entries = read_entries(&self.path)?;
+        entries.retain(|entry| !self.matches(entry, prompt, target));
+        entries.push(Entry {
+            prompt: normalize(prompt),
+            model: self.model.clone(),
+            system_prompt: self.system_prompt(target).to_string(),
+            answer: answer.to_string()
+        });
+        write_entries(&self.path, &entries).map_err(|e| format!("Could not write {}: {e}", self.path.display()))
+    }
+}
+
+impl TranslationBackend for CachedBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            if !session.exchanges().is_empty() || !corrections.is_empty() {
+                return self.inner.translate(prompt, target, session, corrections).await;
+            }
+            if let Some(entry) = read_entries(&self.path)?.into_iter().find(|entry| self.matches(entry, prompt, target)) {
+                return Ok(entry.answer);
+            }
+            let answer = self.inner.translate(prompt, target, session, corrections).await?;
+            if let Err(e) = self.store(prompt, target, &answer) {
+                eprintln!("{e}");
+            }
+            Ok(answer)
+        })
+    }
+
+    /// Passes while translations recorded for this model can be replayed, even with the model unreachable.
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let error = match self.inner.check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => e
+            };
+            if !read_entries(&self.path)?.iter().any(|entry| entry.model == self.model) {
+                return Err(error);
+            }
+            eprintln!("{error}; only recorded translations can be replayed");
+            Ok(())
+        })
+    }
+}
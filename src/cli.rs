@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use speedy2d::color::Color;
+use crate::diagrams::DiagramStyle;
+use crate::decoding::{is_lambda_term, parse_expression};
+use crate::backend::{interpret_query, Target, TranslationBackend};
+use crate::config::{read_system_prompt, ConfigLayer, EnglishMode};
+use crate::prelude::parse_prelude_term;
+use crate::session::Session;
+use crate::expr::LambdaExpr;
+use crate::reduction::{EtaRule, ReductionOptions};
+use crate::export::ExportOptions;
+
+pub(crate) enum Command {
+    Window,
+    Bench,
+    Svg(String, PathBuf),
+    Png(String, PathBuf),
+    Animate(String, PathBuf),
+    Text(String),
+    Eta(String),
+    ServeMock(PathBuf, u16),
+    Repl,
+}
+
+pub(crate) struct Args {
+    pub(crate) command: Command,
+    pub(crate) reduction: ReductionOptions,
+    pub(crate) export: ExportOptions,
+    pub(crate) trace: bool,
+    pub(crate) filmstrip: bool,
+    pub(crate) backend: String,
+    pub(crate) target: Target,
+    pub(crate) config_path: Option<PathBuf>,
+    pub(crate) config: ConfigLayer,
+    pub(crate) clear_cache: bool,
+}
+
+fn parse_workers(value: Option<String>) -> Result<usize, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) if x > 0 => Ok(x),
+        _ => Err("--workers expects a positive integer".to_string())
+    }
+}
+
+fn parse_eta(value: Option<String>) -> Result<EtaRule, String> {
+    match value.as_deref() {
+        Some("off") => Ok(EtaRule::Off),
+        Some("reduce") => Ok(EtaRule::Reduce),
+        Some("long") => Ok(EtaRule::Long(2)),
+        Some(x) => match x.strip_prefix("long:").and_then(|n| n.parse().ok()) {
+            Some(n) if n > 0 => Ok(EtaRule::Long(n)),
+            _ => Err("--eta expects one of off, reduce, long or long:N".to_string())
+        },
+        None => Err("--eta expects one of off, reduce, long or long:N".to_string())
+    }
+}
+
+fn parse_thickness(value: Option<String>) -> Result<f32, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) if x > 0.0 => Ok(x),
+        _ => Err("--thickness expects a positive number".to_string())
+    }
+}
+
+fn parse_color(value: Option<String>) -> Result<Color, String> {
+    match value.and_then(|x| u32::from_str_radix(x.trim_start_matches('#'), 16).ok()) {
+        Some(x) => Ok(Color::from_hex_rgb(x)),
+        None => Err("Colors are written as hex, e.g. #ffffff".to_string())
+    }
+}
+
+fn parse_size(value: Option<String>) -> Result<(u32, u32), String> {
+    let size = value.and_then(|x| {
+        let (width, height) = x.split_once('x')?;
+        Some((width.parse().ok()?, height.parse().ok()?))
+    });
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err("--size expects WIDTHxHEIGHT, e.g. 1920x1080".to_string())
+    }
+}
+
+fn parse_style(value: Option<String>) -> Result<DiagramStyle, String> {
+    match value.as_deref() {
+        Some("tromp") => Ok(DiagramStyle::Tromp),
+        Some("tree") => Ok(DiagramStyle::Tree),
+        Some("graph") => Ok(DiagramStyle::Graph),
+        _ => Err("--style expects one of tromp, tree, graph".to_string())
+    }
+}
+
+fn is_query(input: &str) -> bool {
+    !is_lambda_term(input) && !input.replace("ans", "").chars().all(|c| c.is_ascii_digit() || "+-*/!()$ ".contains(c))
+}
+
+fn parse_input(input: &str, target: Target) -> Result<LambdaExpr, String> {
+    if target == Target::Lambda && is_lambda_term(input) {
+        parse_prelude_term(input, &Session::default())
+    } else {
+        parse_expression(input)
+    }
+}
+
+/// How the normal form of `input` is read back: queries by their target, arithmetic as a numeral, terms not at all.
+pub(crate) fn result_target(input: &str, target: Target) -> Option<Target> {
+    if is_query(input) {
+        Some(target)
+    } else {
+        (!is_lambda_term(input)).then_some(Target::Arithmetic)
+    }
+}
+
+/// Reads one line of a session: queries go through `backend`, terms and arithmetic are parsed
+/// directly. Returns the term and the answer to remember for follow-ups.
+pub(crate) async fn read_session_input(input: &str, target: Target, session: &Session, backend: &dyn TranslationBackend) -> Result<(LambdaExpr, String), String> {
+    if is_query(input) {
+        interpret_query(input, target, session, backend).await
+    } else {
+        session.parse_expression(input, target == Target::Lambda).map(|term| (term, input.to_string()))
+    }
+}
+
+pub(crate) async fn load_input(input: &str, target: Target, backend: &dyn TranslationBackend) -> Result<LambdaExpr, String> {
+    if !is_query(input) {
+        return parse_input(input, target);
+    }
+    backend.check().await?;
+    interpret_query(input, target, &Session::default(), backend).await.map(|(term, _)| term)
+}
+
+fn parse_port(value: Option<String>) -> Result<u16, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) => Ok(x),
+        None => Err("--port expects a number between 0 and 65535".to_string())
+    }
+}
+
+fn parse_temperature(value: Option<String>) -> Result<f32, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) => Ok(x),
+        None => Err("--temperature expects a number, e.g. 0.2".to_string())
+    }
+}
+
+fn parse_timeout(value: Option<String>) -> Result<u64, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) => Ok(x),
+        None => Err("--timeout expects a number of seconds".to_string())
+    }
+}
+
+fn parse_retries(value: Option<String>) -> Result<usize, String> {
+    match value.and_then(|x| x.parse().ok()) {
+        Some(x) => Ok(x),
+        None => Err("--retries expects a number of extra attempts, e.g. 2".to_string())
+    }
+}
+
+fn parse_english(value: Option<String>) -> Result<EnglishMode, String> {
+    match value.map(|x| x.parse()) {
+        Some(Ok(x)) => Ok(x),
+        _ => Err("--english expects first, fallback or off".to_string())
+    }
+}
+
+fn parse_system_prompt(value: Option<String>) -> Result<String, String> {
+    read_system_prompt(Path::new(&value.ok_or("--system-prompt expects a file")?))
+}
+
+pub(crate) fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut reduction = ReductionOptions::default();
+    let mut export = ExportOptions::default();
+    let mut trace = false;
+    let mut filmstrip = false;
+    let mut backend = String::from("ollama");
+    let mut port = 11434;
+    let mut target = Target::Arithmetic;
+    let mut config_path = None;
+    let mut config = ConfigLayer::default();
+    let mut clear_cache = false;
+    let mut positional = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--workers" => reduction.workers = parse_workers(args.next())?,
+            "--eta" => reduction.eta = parse_eta(args.next())?,
+            "--trace" => trace = true,
+            "--filmstrip" => filmstrip = true,
+            "--size" => export.size = Some(parse_size(args.next())?),
+            "--thickness" => export.thickness = parse_thickness(args.next())?,
+            "--color" => export.color = Some(parse_color(args.next())?),
+            "--background" => export.background = Some(parse_color(args.next())?),
+            "--transparent" => export.background = None,
+            "--style" => export.style = parse_style(args.next())?,
+            "--backend" => backend = args.next().ok_or("--backend expects ollama, mock:<fixture> or http://host:port")?,
+            "--port" => port = parse_port(args.next())?,
+            "--config" => config_path = Some(PathBuf::from(args.next().ok_or("--config expects a file")?)),
+            "--model" => config.model = Some(args.next().ok_or("--model expects a model name, e.g. llama3:latest")?),
+            "--ollama-host" => config.host = Some(args.next().ok_or("--ollama-host expects a URL, e.g. http://127.0.0.1")?),
+            "--ollama-port" => config.port = Some(parse_port(args.next())?),
+            "--temperature" => config.temperature = Some(parse_temperature(args.next())?),
+            "--timeout" => config.timeout = Some(parse_timeout(args.next())?),
+            "--retries" => config.retries = Some(parse_retries(args.next())?),
+            "--free-text" => config.structured = Some(false),
+            "--no-cache" => config.cache = Some(false),
+            "--cache-file" => {
+                config.cache_file = Some(PathBuf::from(args.next().ok_or("--cache-file expects a file")?));
+                config.cache = Some(true);
+            }
+            "--clear-cache" => clear_cache = true,
+            "--english" => config.english = Some(parse_english(args.next())?),
+            "--lambda" => target = Target::Lambda,
+            "--system-prompt" => config.system_prompt = Some(parse_system_prompt(args.next())?),
+            x if x.starts_with("--") => return Err(format!("Unknown argument {x}")),
+            _ => positional.push(arg)
+        }
+    }
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        None => Command::Window,
+        Some("bench") => {
+            reduction.workers = reduction.workers.max(2);
+            Command::Bench
+        }
+        Some("svg") => match (positional.next(), positional.next()) {
+            (Some(input), Some(output)) => Command::Svg(input, PathBuf::from(output)),
+            _ => return Err("Usage: svg <term or arithmetic> <output> [--trace]".to_string())
+        },
+        Some("png") => match (positional.next(), positional.next()) {
+            (Some(input), Some(output)) => Command::Png(input, PathBuf::from(output)),
+            _ => return Err("Usage: png <term or arithmetic> <output> [--trace | --filmstrip]".to_string())
+        },
+        Some("text") => match positional.next() {
+            Some(input) => Command::Text(input),
+            None => return Err("Usage: text <term or arithmetic> [--trace]".to_string())
+        },
+        Some("eta") => match positional.next() {
+            Some(input) => Command::Eta(input),
+            None => return Err("Usage: eta <term or arithmetic> [--eta long:N]".to_string())
+        },
+        Some("animate") => match (positional.next(), positional.next()) {
+            (Some(input), Some(output)) => Command::Animate(input, PathBuf::from(output)),
+            _ => return Err("Usage: animate <query, term or arithmetic> <output.gif | output.png | directory>".to_string())
+        },
+        Some("repl") => Command::Repl,
+        Some("serve-mock") => match positional.next() {
+            Some(fixture) => Command::ServeMock(PathBuf::from(fixture), port),
+            None => return Err("Usage: serve-mock <fixture> [--port N]".to_string())
+        },
+        Some(x) => return Err(format!("Unknown command {x}"))
+    };
+    Ok(Args {
+        command,
+        reduction,
+        export,
+        trace,
+        filmstrip,
+        backend,
+        target,
+        config_path,
+        config,
+        clear_cache,
+    })
+}
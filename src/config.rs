@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use crate::ollama::{DEFAULT_MODEL, SYSTEM_PROMPT_0, SYSTEM_PROMPT_STRUCTURED};
+
+const CONFIG_FILE: &str = ".config/lambda/config.toml";
+const CACHE_FILE: &str = "lambda/translations.json";
+const DEFAULT_HOST: &str = "http://127.0.0.1";
+const DEFAULT_PORT: u16 = 11434;
+const DEFAULT_TIMEOUT: u64 = 60;
+const DEFAULT_RETRIES: usize = 2;
+const MAX_TEMPERATURE: f32 = 2.0;
+
+/// When the built-in English parser answers instead of the model.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EnglishMode {
+    First,
+    Fallback,
+    Off
+}
+
+impl std::str::FromStr for EnglishMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "first" => Ok(EnglishMode::First),
+            "fallback" => Ok(EnglishMode::Fallback),
+            "off" => Ok(EnglishMode::Off),
+            x => Err(format!("Expected first, fallback or off, got {x}"))
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Example {
+    pub(crate) user: String,
+    pub(crate) assistant: String
+}
+
+/// One source of settings; unset fields fall through to the layer below.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ConfigLayer {
+    pub(crate) host: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) model: Option<String>,
+    pub(crate) temperature: Option<f32>,
+    pub(crate) timeout: Option<u64>,
+    pub(crate) retries: Option<usize>,
+    pub(crate) structured: Option<bool>,
+    pub(crate) english: Option<EnglishMode>,
+    pub(crate) cache: Option<bool>,
+    pub(crate) cache_file: Option<PathBuf>,
+    pub(crate) system_prompt: Option<String>,
+    pub(crate) examples: Option<Vec<Example>>
+}
+
+impl ConfigLayer {
+    fn over(self, base: ConfigLayer) -> ConfigLayer {
+        ConfigLayer {
+            host: self.host.or(base.host),
+            port: self.port.or(base.port),
+            model: self.model.or(base.model),
+            temperature: self.temperature.or(base.temperature),
+            timeout: self.timeout.or(base.timeout),
+            retries: self.retries.or(base.retries),
+            structured: self.structured.or(base.structured),
+            english: self.english.or(base.english),
+            cache: self.cache.or(base.cache),
+            cache_file: self.cache_file.or(base.cache_file),
+            system_prompt: self.system_prompt.or(base.system_prompt),
+            examples: self.examples.or(base.examples)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Config {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) model: String,
+    pub(crate) temperature: Option<f32>,
+    pub(crate) timeout: Duration,
+    pub(crate) retries: usize,
+    pub(crate) structured: bool,
+    pub(crate) english: EnglishMode,
+    /// Where translations are remembered, unless caching is off.
+    pub(crate) cache: Option<PathBuf>,
+    pub(crate) system_prompt: String,
+    pub(crate) examples: Vec<Example>
+}
+
+impl Config {
+    pub(crate) fn url(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.host.starts_with("http://") && !self.host.starts_with("https://") {
+            return Err(format!("host must start with http:// or https://, got {}", self.host));
+        }
+        if self.model.trim().is_empty() {
+            return Err("model must not be empty".to_string());
+        }
+        if let Some(t) = self.temperature {
+            if !(0.0..=MAX_TEMPERATURE).contains(&t) {
+                return Err(format!("temperature must be between 0 and {MAX_TEMPERATURE}, got {t}"));
+            }
+        }
+        if self.timeout.is_zero() {
+            return Err("timeout must be at least one second".to_string());
+        }
+        if self.system_prompt.trim().is_empty() {
+            return Err("system_prompt must not be empty".to_string());
+        }
+        match self.examples.iter().position(|x| x.user.trim().is_empty() || x.assistant.trim().is_empty()) {
+            Some(i) => Err(format!("example {} needs both a user and an assistant line", i + 1)),
+            None => Ok(())
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(CONFIG_FILE)).filter(|path| path.exists())
+}
+
+/// `$XDG_CACHE_HOME/lambda/translations.json`, falling back to `~/.cache`.
+fn default_cache_file() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join(CACHE_FILE))
+}
+
+fn read_file(path: &Path) -> Result<ConfigLayer, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    toml::from_str(&contents).map_err(|e| format!("Invalid config {}: {e}", path.display()))
+}
+
+/// Reads a system prompt from a file, as `--system-prompt` and `LAMBDA_SYSTEM_PROMPT_FILE` take it.
+pub(crate) fn read_system_prompt(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))
+}
+
+fn env_var<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|_| format!("Could not parse {name}={value}")),
+        Err(_) => Ok(None)
+    }
+}
+
+fn read_env() -> Result<ConfigLayer, String> {
+    Ok(ConfigLayer {
+        host: env_var("LAMBDA_OLLAMA_HOST")?,
+        port: env_var("LAMBDA_OLLAMA_PORT")?,
+        model: env_var("LAMBDA_MODEL")?,
+        temperature: env_var("LAMBDA_TEMPERATURE")?,
+        timeout: env_var("LAMBDA_TIMEOUT")?,
+        retries: env_var("LAMBDA_RETRIES")?,
+        structured: env_var("LAMBDA_STRUCTURED")?,
+        english: env_var("LAMBDA_ENGLISH")?,
+        cache: env_var("LAMBDA_CACHE")?,
+        cache_file: env_var("LAMBDA_CACHE_FILE")?,
+        system_prompt: env_var::<PathBuf>("LAMBDA_SYSTEM_PROMPT_FILE")?.map(|path| read_system_prompt(&path)).transpose()?,
+        examples: None
+    })
+}
+
+/// Resolves settings from the defaults, the config file, `LAMBDA_*` variables and finally `flags`.
+pub(crate) fn load_config(path: Option<&Path>, flags: ConfigLayer) -> Result<Config, String> {
+    let path = path.map(Path::to_path_buf)
+        .or_else(|| std::env::var_os("LAMBDA_CONFIG").map(PathBuf::from))
+        .or_else(default_path);
+    let file = match path {
+        Some(path) => read_file(&path)?,
+        None => ConfigLayer::default()
+    };
+    let layer = flags.over(read_env()?).over(file);
+    let structured = layer.structured.unwrap_or(true);
+    let default_prompt = if structured { SYSTEM_PROMPT_STRUCTURED } else { SYSTEM_PROMPT_0 };
+    let config = Config {
+        host: layer.host.unwrap_or(DEFAULT_HOST.to_string()).trim_end_matches('/').to_string(),
+        port: layer.port.unwrap_or(DEFAULT_PORT),
+        model: layer.model.unwrap_or(DEFAULT_MODEL.to_string()),
+        temperature: layer.temperature,
+        timeout: Duration::from_secs(layer.timeout.unwrap_or(DEFAULT_TIMEOUT)),
+        retries: layer.retries.unwrap_or(DEFAULT_RETRIES),
+        structured,
+        english: layer.english.unwrap_or(EnglishMode::Fallback),
+        cache: if layer.cache.unwrap_or(true) { layer.cache_file.or_else(default_cache_file) } else { None },
+        system_prompt: layer.system_prompt.unwrap_or(default_prompt.to_string()),
+        examples: layer.examples.unwrap_or_default()
+    };
+    config.validate().map_err(|e| format!("Invalid configuration: {e}"))?;
+    Ok(config)
+}
@@ -1,5 +1,9 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
 use crate::expr::{ExprType, LambdaExpr};
 use crate::numerals::church;
+use crate::prelude::closed;
+use crate::structured::ArithmeticNode;
 
 pub(crate) fn interpret_expr(input: &String) -> Option<LambdaExpr> {
     if input.matches('(').count() != input.matches(')').count() {
@@ -28,7 +32,7 @@ pub(crate) fn interpret_expr(input: &String) -> Option<LambdaExpr> {
         }
     }
     let chars = input_clone.chars();
-    if input_clone.len() == 1 {
+    if input_clone.chars().count() == 1 {
         Some(LambdaExpr {
             expr_type: ExprType::Var,
             id: input_clone.chars().nth(0)? as usize,
@@ -63,55 +67,147 @@ pub(crate) fn interpret_expr(input: &String) -> Option<LambdaExpr> {
     }
 }
 
-pub(crate) fn arithmetic_to_lambda(input: &String) -> LambdaExpr {
-    let mut words: Vec<String> = input.split(" ").map(|x| x.to_string()).collect();
-    if words.len() == 1 {
-        church(words[0].parse().unwrap())
-    } else {
-        let add = interpret_expr(&"λm.λn.λf.λx.mf(nfx)".to_string()).unwrap();
-        let mul = interpret_expr(&"λm.λn.λf.λx.m(nf)x".to_string()).unwrap();
-        let pred = interpret_expr(&"λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u)".to_string()).unwrap();
-        let sub = interpret_expr(&format!("λm.λn.n({pred})m")).unwrap();
-        let div = interpret_expr(&"(λn.((λf.(λx.xx)(λx.f(xx)))(λc.λn.λm.λf.λx.(λd.(λn.n(λx.(λa.λb.b))(λa.λb.a))d((λf.λx.x)fx)(f(cdmfx)))((λm.λn.n(λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u))m)nm)))((λn.λf.λx.f(nfx))n))".to_string()).unwrap();
-        let iszero = interpret_expr(&"λn.n(λx.(λa.λb.b))(λa.λb.a)".to_string()).unwrap();
-        let fac = interpret_expr(&format!("(λx.(λy.x(yy))(λy.x(yy)))(λf.λn.(({iszero})n)({})(({mul})n(f(({pred})n))))", church(1))).unwrap();
-        let last_word = words.pop().unwrap();
-        let op_string = if last_word != "!" {
-            words.pop().unwrap()
-        } else {
-            last_word.clone()
-        };
-        let op = op_string.as_str();
-        let op_lambda = match op {
-            "+" => add,
-            "-" => sub,
-            "*" => mul,
-            "/" => div,
-            "!" => fac,
-            x => panic!("Unknown operation {x}")
-        };
-        LambdaExpr {
-            expr_type: ExprType::App,
-            id: 0,
-            children: if last_word == "!" {
-                vec![
-                    op_lambda,
-                    arithmetic_to_lambda(&words.join(" "))
-                ]
-            } else {
-                vec![
-                    LambdaExpr {
-                        expr_type: ExprType::App,
-                        id: 0,
-                        children:
-                        vec![
-                            op_lambda,
-                            arithmetic_to_lambda(&words.join(" "))
-                        ],
-                    },
-                    church(last_word.parse().unwrap())
-                ]
+pub(crate) fn combinator(op: &str) -> Option<LambdaExpr> {
+    let add = "λm.λn.λf.λx.mf(nfx)";
+    let mul = "λm.λn.λf.λx.m(nf)x";
+    let pred = "λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u)";
+    let iszero = "λn.n(λx.(λa.λb.b))(λa.λb.a)";
+    let source = match op {
+        "+" => add.to_string(),
+        "-" => format!("λm.λn.n({pred})m"),
+        "*" => mul.to_string(),
+        "/" => "(λn.((λf.(λx.xx)(λx.f(xx)))(λc.λn.λm.λf.λx.(λd.(λn.n(λx.(λa.λb.b))(λa.λb.a))d((λf.λx.x)fx)(f(cdmfx)))((λm.λn.n(λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u))m)nm)))((λn.λf.λx.f(nfx))n))".to_string(),
+        "!" => format!("(λx.(λy.x(yy))(λy.x(yy)))(λf.λn.(({iszero})n)({})(({mul})n(f(({pred})n))))", church(1)),
+        _ => return None
+    };
+    interpret_expr(&source)
+}
+
+enum Token {
+    Number(i32),
+    Op(char),
+    Open,
+    Close
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' => {}
+            '0'..='9' => {
+                let mut number = c.to_string();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                tokens.push(Token::Number(number.parse().map_err(|_| format!("{number} is too large"))?));
+            }
+            '+' | '-' | '*' | '/' | '!' => tokens.push(Token::Op(c)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            _ => return Err(format!("Unexpected character '{c}'"))
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+pub(crate) fn apply_combinator(op: char, args: Vec<LambdaExpr>) -> LambdaExpr {
+    args.into_iter().fold(combinator(&op.to_string()).unwrap(), |f, arg| LambdaExpr {
+        expr_type: ExprType::App,
+        id: 0,
+        children: vec![f, arg],
+    })
+}
+
+fn parse_sum(tokens: &mut Tokens) -> Result<ArithmeticNode, String> {
+    let mut node = parse_product(tokens)?;
+    while let Some(Token::Op(op)) = tokens.next_if(|x| matches!(x, Token::Op('+' | '-'))) {
+        node = ArithmeticNode::operation(op, vec![node, parse_product(tokens)?]);
+    }
+    Ok(node)
+}
+
+fn parse_product(tokens: &mut Tokens) -> Result<ArithmeticNode, String> {
+    let mut node = parse_factorial(tokens)?;
+    while let Some(Token::Op(op)) = tokens.next_if(|x| matches!(x, Token::Op('*' | '/'))) {
+        node = ArithmeticNode::operation(op, vec![node, parse_factorial(tokens)?]);
+    }
+    Ok(node)
+}
+
+fn parse_factorial(tokens: &mut Tokens) -> Result<ArithmeticNode, String> {
+    let mut node = parse_atom(tokens)?;
+    while tokens.next_if(|x| matches!(x, Token::Op('!'))).is_some() {
+        node = ArithmeticNode::operation('!', vec![node]);
+    }
+    Ok(node)
+}
+
+fn parse_atom(tokens: &mut Tokens) -> Result<ArithmeticNode, String> {
+    match tokens.next() {
+        Some(Token::Number(x)) => Ok(ArithmeticNode::number(x)),
+        Some(Token::Open) => {
+            let node = parse_sum(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(node),
+                _ => Err("Missing closing parenthesis".to_string())
             }
         }
+        Some(Token::Op(op)) => Err(format!("Expected a number before '{op}'")),
+        Some(Token::Close) => Err("Unexpected ')'".to_string()),
+        None => Err("Expected a number at the end".to_string())
+    }
+}
+
+/// Reads arithmetic into the same expression tree a structured answer is read into.
+fn parse_arithmetic_tree(input: &str) -> Result<ArithmeticNode, String> {
+    let mut tokens = tokenize(input)?.into_iter().peekable();
+    let node = parse_sum(&mut tokens)?;
+    match tokens.next() {
+        None => Ok(node),
+        Some(Token::Close) => Err("Unexpected ')'".to_string()),
+        Some(_) => Err("Expected an operator between numbers".to_string())
+    }
+}
+
+pub(crate) fn parse_arithmetic(input: &str) -> Result<LambdaExpr, String> {
+    parse_arithmetic_tree(input)?.to_lambda()
+}
+
+pub(crate) fn is_lambda_term(input: &str) -> bool {
+    input.contains(['λ', '\\'])
+}
+
+/// Like [`parse_expression`], but leaves free variables in lambda terms for the caller to fill in.
+pub(crate) fn parse_open_expression(input: &str) -> Result<LambdaExpr, String> {
+    if is_lambda_term(input) {
+        let term = input.replace('\\', "λ").replace(' ', "");
+        interpret_expr(&term).ok_or_else(|| format!("Could not parse the term {term}"))
+    } else {
+        parse_arithmetic(input)
     }
-}
\ No newline at end of file
+}
+
+/// Reads a closed lambda term or arithmetic; a free variable would leave the diagram nothing to point at.
+pub(crate) fn parse_expression(input: &str) -> Result<LambdaExpr, String> {
+    parse_open_expression(input).and_then(closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> LambdaExpr {
+        parse_arithmetic(input).unwrap()
+    }
+
+    #[test]
+    fn division_binds_tighter_than_addition() {
+        assert_eq!(parse("3 + 7 / 12"), parse("3 + (7 / 12)"));
+        assert_ne!(parse("3 + 7 / 12"), parse("(3 + 7) / 12"));
+        assert_eq!(parse("2 * 3! - 4 / 2 - 1"), parse("((2 * (3!)) - (4 / 2)) - 1"));
+    }
+}
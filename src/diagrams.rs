@@ -1,17 +1,52 @@
 use std::collections::HashMap;
 use crate::expr::{ExprType, LambdaExpr};
+use crate::tree_diagrams::{construct_graph_diagram, construct_tree_diagram};
 
-const PADDING: f32 = 10.0;
+pub(crate) const PADDING: f32 = 10.0;
+pub(crate) const LABEL_SIZE: f32 = 12.0;
+const ARROW_SIZE: f32 = PADDING / 2.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum DiagramStyle {
+    Tromp,
+    Tree,
+    Graph
+}
+
+impl DiagramStyle {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            DiagramStyle::Tromp => DiagramStyle::Tree,
+            DiagramStyle::Tree => DiagramStyle::Graph,
+            DiagramStyle::Graph => DiagramStyle::Tromp
+        }
+    }
+}
 
 pub(crate) enum Direction {
     Vertical,
     Horizontal
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum LineKind {
+    Variable,
+    Binder,
+    ApplicationLeft,
+    ApplicationRight,
+    ApplicationBar,
+    TreeStem,
+    TreeBar,
+    TreeEdge(usize),
+    Binding
+}
+
 pub(crate) struct Line {
     pub(crate) origin: (f32, f32),
     pub(crate) length: f32,
-    pub(crate) direction: Direction
+    pub(crate) direction: Direction,
+    pub(crate) source: Vec<usize>,
+    pub(crate) kind: LineKind
 }
 
 impl Line {
@@ -23,8 +58,30 @@ impl Line {
     }
 }
 
+pub(crate) struct Label {
+    pub(crate) position: (f32, f32),
+    pub(crate) text: String,
+    pub(crate) source: Vec<usize>
+}
+
+pub(crate) struct Arrowhead {
+    pub(crate) tip: (f32, f32),
+    pub(crate) leftward: bool,
+    pub(crate) source: Vec<usize>
+}
+
+impl Arrowhead {
+    pub(crate) fn strokes(&self) -> [((f32, f32), (f32, f32)); 2] {
+        let back = if self.leftward { self.tip.0 + ARROW_SIZE } else { self.tip.0 - ARROW_SIZE };
+        [((back, self.tip.1 - ARROW_SIZE), self.tip), ((back, self.tip.1 + ARROW_SIZE), self.tip)]
+    }
+}
+
+#[derive(Default)]
 pub(crate) struct Diagram {
-    pub(crate) lines: Vec<Line>
+    pub(crate) lines: Vec<Line>,
+    pub(crate) labels: Vec<Label>,
+    pub(crate) arrowheads: Vec<Arrowhead>
 }
 
 impl Diagram {
@@ -37,6 +94,12 @@ impl Diagram {
                 y = line.endpoint().1;
             }
         }
+        for label in &self.labels {
+            if label.position.0 + LABEL_SIZE > largest_x {
+                largest_x = label.position.0 + LABEL_SIZE;
+                y = label.position.1;
+            }
+        }
         (largest_x, y)
     }
 
@@ -49,6 +112,12 @@ impl Diagram {
                 x = line.endpoint().0;
             }
         }
+        for label in &self.labels {
+            if label.position.1 + LABEL_SIZE > largest_y {
+                largest_y = label.position.1 + LABEL_SIZE;
+                x = label.position.0;
+            }
+        }
         (x, largest_y)
     }
 
@@ -57,13 +126,23 @@ impl Diagram {
             line.origin.0 += vec.0;
             line.origin.1 += vec.1;
         }
+        for label in &mut self.labels {
+            label.position.0 += vec.0;
+            label.position.1 += vec.1;
+        }
+        for arrowhead in &mut self.arrowheads {
+            arrowhead.tip.0 += vec.0;
+            arrowhead.tip.1 += vec.1;
+        }
     }
 
     fn merge(&mut self, other: Diagram) {
         self.lines.extend(other.lines);
+        self.labels.extend(other.labels);
+        self.arrowheads.extend(other.arrowheads);
     }
 
-    fn add_line(&mut self, line: Line) {
+    pub(crate) fn add_line(&mut self, line: Line) {
         self.lines.push(line);
     }
 
@@ -78,6 +157,7 @@ impl Diagram {
 pub(crate) struct Passthrough {
     var_positions: HashMap<usize, f32>,
     next_position: f32,
+    path: Vec<usize>,
 }
 
 impl Passthrough {
@@ -85,8 +165,15 @@ impl Passthrough {
         Self {
             var_positions: HashMap::default(),
             next_position: 0.0,
+            path: vec![],
         }
     }
+
+    fn child(&self, index: usize) -> Self {
+        let mut p = self.clone();
+        p.path.push(index);
+        p
+    }
 }
 
 pub(crate) fn construct_diagram(expr: &LambdaExpr, p: &Passthrough) -> Diagram {
@@ -96,26 +183,31 @@ pub(crate) fn construct_diagram(expr: &LambdaExpr, p: &Passthrough) -> Diagram {
                 lines: vec![Line{
                     origin: (PADDING, p.var_positions[&expr.id]),
                     length: p.next_position - p.var_positions[&expr.id],
-                    direction: Direction::Vertical
-                }]
+                    direction: Direction::Vertical,
+                    source: p.path.clone(),
+                    kind: LineKind::Variable
+                }],
+                ..Diagram::default()
             }
         }
         ExprType::Abs => {
-            let mut new_p = p.clone();
+            let mut new_p = p.child(0);
             new_p.var_positions.insert(expr.id, p.next_position);
             new_p.next_position += PADDING;
             let mut child = construct_diagram(&expr.children[0], &new_p);
             let var_bar = Line{
                 origin: (0.0, p.next_position),
                 length: child.rightmost().0 + if expr.children[0].expr_type == ExprType::Abs { 0.0 } else { PADDING },
-                direction: Direction::Horizontal
+                direction: Direction::Horizontal,
+                source: p.path.clone(),
+                kind: LineKind::Binder
             };
             child.add_line(var_bar);
             child
         }
         ExprType::App => {
-            let mut a = construct_diagram(&expr.children[0], p);
-            let mut b = construct_diagram(&expr.children[1], p);
+            let mut a = construct_diagram(&expr.children[0], &p.child(0));
+            let mut b = construct_diagram(&expr.children[1], &p.child(1));
             b.shift((a.rightmost().0 + PADDING, 0.0));
             let point_a = a.bottommost();
             let point_b = b.bottommost();
@@ -124,20 +216,34 @@ pub(crate) fn construct_diagram(expr: &LambdaExpr, p: &Passthrough) -> Diagram {
             let line_a = Line{
                 origin: point_a,
                 length: cross_y - point_a.1 + PADDING,
-                direction: Direction::Vertical
+                direction: Direction::Vertical,
+                source: p.path.clone(),
+                kind: LineKind::ApplicationLeft
             };
             let line_b = Line{
                 origin: point_b,
                 length: cross_y - point_b.1,
-                direction: Direction::Vertical
+                direction: Direction::Vertical,
+                source: p.path.clone(),
+                kind: LineKind::ApplicationRight
             };
             let line_cross = Line{
                 origin: (point_a.0, cross_y),
                 length: point_b.0 - point_a.0,
-                direction: Direction::Horizontal
+                direction: Direction::Horizontal,
+                source: p.path.clone(),
+                kind: LineKind::ApplicationBar
             };
             a.add_lines(vec![line_a, line_b, line_cross]);
             a
         }
     }
-}
\ No newline at end of file
+}
+
+pub(crate) fn build_diagram(expr: &LambdaExpr, style: DiagramStyle) -> Diagram {
+    match style {
+        DiagramStyle::Tromp => construct_diagram(expr, &Passthrough::top()),
+        DiagramStyle::Tree => construct_tree_diagram(expr),
+        DiagramStyle::Graph => construct_graph_diagram(expr)
+    }
+}
@@ -0,0 +1,283 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+use futures::future::BoxFuture;
+use crate::backend::{Correction, Target, TranslationBackend};
+use crate::session::Session;
+use crate::structured::ArithmeticNode;
+
+const UNITS: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen"
+];
+const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+const SCALES: [(&str, u64); 3] = [("hundred", 100), ("thousand", 1000), ("million", 1000000)];
+/// Words that only dress up a question, e.g. "what is", "please", "now".
+const FILLER: [&str; 14] = ["what", "is", "whats", "how", "much", "calculate", "compute", "evaluate", "please", "now", "tell", "me", "equal", "equals"];
+/// Words that refer to the previous result.
+const REFERENCES: [&str; 3] = ["that", "it", "ans"];
+
+fn binary(op: char, a: ArithmeticNode, b: ArithmeticNode) -> ArithmeticNode {
+    ArithmeticNode::operation(op, vec![a, b])
+}
+
+fn squared(node: ArithmeticNode) -> ArithmeticNode {
+    binary('*', node.clone(), node)
+}
+
+/// The number an earlier result holds, for "that" or `$2`.
+fn reference(word: &str, session: &Session) -> Result<ArithmeticNode, String> {
+    let value = session.expand(word, Target::Arithmetic)?;
+    value.parse().map(ArithmeticNode::number).map_err(|_| format!("{word} is {value}, which is too large"))
+}
+
+type Words = Peekable<IntoIter<String>>;
+
+/// Splits on spaces and around symbols; a hyphen after a letter joins words, as in "twenty-three".
+fn tokenize(query: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in query.to_lowercase().chars().chain(std::iter::once(' ')) {
+        let joiner = c == '-' && previous.is_alphabetic();
+        previous = c;
+        if c == '\'' || c == '.' {
+            continue;
+        }
+        if c.is_whitespace() || joiner || "?,".contains(c) || "+-*/()!".contains(c) {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            if !joiner && "+-*/()!".contains(c) {
+                words.push(c.to_string());
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    words
+}
+
+fn expect(words: &mut Words, word: &str) -> Result<(), String> {
+    match words.next() {
+        Some(x) if x == word => Ok(()),
+        Some(x) => Err(format!("Expected \"{word}\" but found \"{x}\"")),
+        None => Err(format!("Expected \"{word}\" at the end"))
+    }
+}
+
+fn skip(words: &mut Words, word: &str) -> bool {
+    words.next_if(|x| x == word).is_some()
+}
+
+fn small_number(word: &str) -> Option<u64> {
+    UNITS.iter().position(|x| *x == word).map(|x| x as u64)
+        .or_else(|| TENS.iter().position(|x| *x == word).map(|x| 20 + 10 * x as u64))
+        .or_else(|| word.parse().ok())
+}
+
+/// The last word [`number`] read, which decides what may come next: "twenty three" is one
+/// number, "two three" and "three twenty" are two.
+#[derive(Clone, Copy, PartialEq)]
+enum Place {
+    Start,
+    Digits,
+    Unit(u64),
+    Tens,
+    Hundred,
+    Scale
+}
+
+fn next_place(place: Place, word: &str) -> Option<Place> {
+    if let Some((_, scale)) = SCALES.iter().find(|(x, _)| *x == word) {
+        let allowed = match place {
+            Place::Unit(x) => x > 0,
+            Place::Digits => true,
+            Place::Tens | Place::Hundred => *scale > 100,
+            Place::Start | Place::Scale => false
+        };
+        return allowed.then_some(if *scale == 100 { Place::Hundred } else { Place::Scale });
+    }
+    if TENS.contains(&word) {
+        return matches!(place, Place::Start | Place::Hundred | Place::Scale).then_some(Place::Tens);
+    }
+    if let Some(x) = UNITS.iter().position(|x| *x == word) {
+        let allowed = match place {
+            Place::Start => true,
+            Place::Tens => (1..10).contains(&x),
+            Place::Hundred | Place::Scale => x > 0,
+            Place::Digits | Place::Unit(_) => false
+        };
+        return allowed.then_some(Place::Unit(x as u64));
+    }
+    (place == Place::Start && word.parse::<u64>().is_ok()).then_some(Place::Digits)
+}
+
+/// Reads "twenty three", "one hundred and five", "a thousand" or "42", stopping at a word that
+/// can't continue the number.
+fn number(words: &mut Words) -> Option<u64> {
+    let mut place = Place::Start;
+    if words.peek().is_some_and(|x| x == "a") {
+        let mut lookahead = words.clone();
+        lookahead.next();
+        if !lookahead.peek().is_some_and(|x| SCALES.iter().any(|(scale, _)| scale == x)) {
+            return None;
+        }
+        words.next();
+        place = Place::Unit(1);
+    }
+    let (mut total, mut current) = (0, 0);
+    while let Some(next) = words.peek().and_then(|x| next_place(place, x)) {
+        let word = words.next()?;
+        place = next;
+        match SCALES.iter().find(|(x, _)| *x == word) {
+            Some((_, 100)) => current = current.max(1) * 100,
+            Some((_, scale)) => {
+                total += current.max(1) * scale;
+                current = 0;
+            }
+            None => current += small_number(&word)?
+        }
+        if matches!(place, Place::Hundred | Place::Scale) && words.peek().is_some_and(|x| x == "and") {
+            let mut lookahead = words.clone();
+            lookahead.next();
+            if lookahead.peek().is_some_and(|x| next_place(place, x).is_some_and(|x| matches!(x, Place::Unit(_) | Place::Tens))) {
+                words.next();
+            }
+        }
+    }
+    (place != Place::Start).then_some(total + current)
+}
+
+fn sum(words: &mut Words, session: &Session) -> Result<ArithmeticNode, String> {
+    let mut node = product(words, session)?;
+    loop {
+        let op = match words.peek().map(String::as_str) {
+            Some("plus" | "+") => '+',
+            Some("minus" | "-") => '-',
+            _ => return Ok(node)
+        };
+        words.next();
+        node = binary(op, node, product(words, session)?);
+    }
+}
+
+fn product(words: &mut Words, session: &Session) -> Result<ArithmeticNode, String> {
+    let mut node = postfix(words, session)?;
+    loop {
+        let op = match words.peek().map(String::as_str) {
+            Some("times" | "*") => '*',
+            Some("multiplied") => {
+                words.next();
+                expect(words, "by")?;
+                node = binary('*', node, postfix(words, session)?);
+                continue;
+            }
+            Some("divided") => {
+                words.next();
+                expect(words, "by")?;
+                node = binary('/', node, postfix(words, session)?);
+                continue;
+            }
+            Some("over" | "/") => '/',
+            _ => return Ok(node)
+        };
+        words.next();
+        node = binary(op, node, postfix(words, session)?);
+    }
+}
+
+fn postfix(words: &mut Words, session: &Session) -> Result<ArithmeticNode, String> {
+    let mut node = atom(words, session)?;
+    loop {
+        match words.peek().map(String::as_str) {
+            Some("factorial" | "!") => node = ArithmeticNode::operation('!', vec![node]),
+            Some("squared") => node = squared(node),
+            _ => return Ok(node)
+        }
+        words.next();
+    }
+}
+
+/// "the sum of X and Y", "the difference between X and Y", ...
+fn two_operands(words: &mut Words, session: &Session, op: char) -> Result<ArithmeticNode, String> {
+    if !skip(words, "of") {
+        expect(words, "between")?;
+    }
+    let a = sum(words, session)?;
+    expect(words, "and")?;
+    Ok(binary(op, a, sum(words, session)?))
+}
+
+/// "multiply X by Y", "add X to Y", "subtract X from Y", "divide X by Y".
+fn command(words: &mut Words, session: &Session, op: char, joiner: &str) -> Result<ArithmeticNode, String> {
+    let a = sum(words, session)?;
+    if op == '+' && skip(words, "and") {
+        return Ok(binary(op, a, sum(words, session)?));
+    }
+    expect(words, joiner)?;
+    let b = sum(words, session)?;
+    Ok(if op == '-' { binary(op, b, a) } else { binary(op, a, b) })
+}
+
+fn atom(words: &mut Words, session: &Session) -> Result<ArithmeticNode, String> {
+    if let Some(x) = number(words) {
+        return i32::try_from(x).map(ArithmeticNode::number).map_err(|_| format!("{x} is too large"));
+    }
+    skip(words, "the");
+    let word = words.next().ok_or("Expected a number at the end")?;
+    match word.as_str() {
+        "sum" => two_operands(words, session, '+'),
+        "difference" => two_operands(words, session, '-'),
+        "product" => two_operands(words, session, '*'),
+        "quotient" => two_operands(words, session, '/'),
+        "factorial" => {
+            expect(words, "of")?;
+            Ok(ArithmeticNode::operation('!', vec![postfix(words, session)?]))
+        }
+        "square" => {
+            expect(words, "of")?;
+            Ok(squared(postfix(words, session)?))
+        }
+        "add" => command(words, session, '+', "to"),
+        "subtract" => command(words, session, '-', "from"),
+        "multiply" => command(words, session, '*', "by"),
+        "divide" => command(words, session, '/', "by"),
+        "(" => {
+            let node = sum(words, session)?;
+            expect(words, ")")?;
+            Ok(node)
+        }
+        x if REFERENCES.contains(&x) => reference("ans", session),
+        x if x.starts_with('$') && x.len() > 1 => reference(x, session),
+        x => Err(format!("Unknown word \"{x}\""))
+    }
+}
+
+/// Reads common English arithmetic phrasing, e.g. "what is twenty-three plus the square of four",
+/// into an expression tree for `23 + 4 * 4`, with "that" and `$n` replaced by the numbers in `session`.
+pub(crate) fn parse_english(query: &str, session: &Session) -> Result<ArithmeticNode, String> {
+    let mut words = tokenize(query).into_iter().peekable();
+    while words.next_if(|x| FILLER.contains(&x.as_str())).is_some() {}
+    let node = sum(&mut words, session)?;
+    while words.next_if(|x| FILLER.contains(&x.as_str())).is_some() {}
+    match words.next() {
+        None => Ok(node),
+        Some(x) => Err(format!("Unexpected \"{x}\""))
+    }
+}
+
+/// Answers arithmetic questions offline with [`parse_english`], as a JSON expression tree.
+pub(crate) struct EnglishBackend;
+
+impl TranslationBackend for EnglishBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, _: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            match target {
+                Target::Arithmetic => parse_english(prompt, session)
+                    .map(|node| serde_json::to_string(&node).unwrap())
+                    .map_err(|e| format!("The built-in English parser could not read it: {e}")),
+                Target::Lambda => Err("The built-in English parser only reads arithmetic".to_string())
+            }
+        })
+    }
+}
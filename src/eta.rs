@@ -0,0 +1,99 @@
+use crate::expr::{ExprType, LambdaExpr};
+use crate::reduction::{Rule, StepInfo};
+
+pub(crate) fn is_free(expr: &LambdaExpr, id: usize) -> bool {
+    match expr.expr_type {
+        ExprType::Var => expr.id == id,
+        ExprType::Abs => expr.id != id && is_free(&expr.children[0], id),
+        ExprType::App => is_free(&expr.children[0], id) || is_free(&expr.children[1], id)
+    }
+}
+
+fn occurs(expr: &LambdaExpr, id: usize) -> bool {
+    (expr.expr_type != ExprType::App && expr.id == id) || expr.children.iter().any(|child| occurs(child, id))
+}
+
+fn fresh_id(expr: &LambdaExpr) -> usize {
+    let mut id = 'v' as usize;
+    while occurs(expr, id) {
+        id += 1000;
+    }
+    id
+}
+
+fn is_eta_redex(expr: &LambdaExpr) -> bool {
+    if expr.expr_type != ExprType::Abs || expr.children[0].expr_type != ExprType::App {
+        return false;
+    }
+    let body = &expr.children[0];
+    body.children[1].expr_type == ExprType::Var && body.children[1].id == expr.id && !is_free(&body.children[0], expr.id)
+}
+
+fn eta_reduce_at(expr: &mut LambdaExpr, path: &mut Vec<usize>, steps: &mut Vec<StepInfo>) {
+    if is_eta_redex(expr) {
+        let size_before = expr.size() as isize;
+        let var = expr.id;
+        *expr = expr.children[0].children[0].clone();
+        steps.push(StepInfo {
+            rule: Rule::Eta,
+            path: path.clone(),
+            var,
+            arg: None,
+            size_delta: expr.size() as isize - size_before,
+        });
+    } else {
+        for (i, child) in expr.children.iter_mut().enumerate() {
+            path.push(i);
+            eta_reduce_at(child, path, steps);
+            path.pop();
+        }
+    }
+}
+
+pub(crate) fn eta_reduce_step(expr: &mut LambdaExpr) -> Vec<StepInfo> {
+    let mut steps = vec![];
+    eta_reduce_at(expr, &mut vec![], &mut steps);
+    steps
+}
+
+pub(crate) fn eta_normal_form(expr: &LambdaExpr) -> LambdaExpr {
+    let mut expr = expr.clone();
+    while !eta_reduce_step(&mut expr).is_empty() {}
+    expr
+}
+
+pub(crate) fn eta_expand(expr: &LambdaExpr) -> LambdaExpr {
+    let id = fresh_id(expr);
+    LambdaExpr {
+        expr_type: ExprType::Abs,
+        id,
+        children: vec![
+            LambdaExpr {
+                expr_type: ExprType::App,
+                id: 0,
+                children: vec![
+                    expr.clone(),
+                    LambdaExpr {
+                        expr_type: ExprType::Var,
+                        id,
+                        children: vec![],
+                    }
+                ]
+            }
+        ]
+    }
+}
+
+pub(crate) fn eta_long(expr: &LambdaExpr, binders: usize) -> LambdaExpr {
+    if binders == 0 {
+        expr.clone()
+    } else if expr.expr_type == ExprType::Abs {
+        LambdaExpr {
+            expr_type: ExprType::Abs,
+            id: expr.id,
+            children: vec![eta_long(&expr.children[0], binders - 1)],
+        }
+    } else {
+        eta_long(&eta_expand(expr), binders)
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs::{self, File};
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+use ab_glyph::FontRef;
+use speedy2d::color::Color;
+use crate::diagrams::DiagramStyle;
+use crate::graphics::{term_text, DELAY, DELAY_START_MULTIPLIER, FONT_BYTES, LINE_THICKNESS};
+use crate::backend::Target;
+use crate::readback::describe_result;
+use crate::raster::{render_frame, Canvas};
+use crate::reduction::Trace;
+
+const FRAME_RATE: u64 = 60;
+const ANIMATION_SIZE: (u32, u32) = (1280, 720);
+
+#[derive(Clone)]
+pub(crate) struct ExportOptions {
+    pub(crate) thickness: f32,
+    pub(crate) color: Option<Color>,
+    pub(crate) background: Option<Color>,
+    pub(crate) size: Option<(u32, u32)>,
+    pub(crate) style: DiagramStyle,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            thickness: LINE_THICKNESS,
+            color: None,
+            background: Some(Color::BLACK),
+            size: None,
+            style: DiagramStyle::Tromp,
+        }
+    }
+}
+
+pub(crate) struct AnimationFrame {
+    pub(crate) canvas: Canvas,
+    pub(crate) frames: u64,
+}
+
+pub(crate) fn render_animation(trace: &Trace, res: &str, options: &ExportOptions) -> Vec<AnimationFrame> {
+    let font = FontRef::try_from_slice(FONT_BYTES).unwrap();
+    let size = options.size.unwrap_or(ANIMATION_SIZE);
+    let mut frames = vec![];
+    for (i, term) in trace.terms.iter().enumerate().take(trace.terms.len() - 1) {
+        frames.push(AnimationFrame {
+            canvas: render_frame(term, &trace.steps[i], false, &term_text(term), &font, size, options),
+            frames: if i == 0 { DELAY * DELAY_START_MULTIPLIER } else { DELAY },
+        });
+    }
+    let normal_form = &trace.terms[trace.terms.len() - 1];
+    for shown in 0..=res.chars().count() {
+        let text = term_text(normal_form) + &res.chars().take(shown).collect::<String>();
+        frames.push(AnimationFrame {
+            canvas: render_frame(normal_form, &[], true, &text, &font, size, options),
+            frames: DELAY,
+        });
+    }
+    frames
+}
+
+fn first_frame(frames: &[AnimationFrame]) -> io::Result<&AnimationFrame> {
+    frames.first().ok_or_else(|| io::Error::other("The animation has no frames"))
+}
+
+fn write_gif(frames: &[AnimationFrame], path: &Path) -> io::Result<()> {
+    let first = first_frame(frames)?;
+    let (width, height) = (first.canvas.width as u16, first.canvas.height as u16);
+    let mut encoder = gif::Encoder::new(BufWriter::new(File::create(path)?), width, height, &[]).map_err(io::Error::other)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+    for frame in frames {
+        let mut rgba = frame.canvas.to_rgba8();
+        let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+        gif_frame.delay = (frame.frames * 100 / FRAME_RATE) as u16;
+        encoder.write_frame(&gif_frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+fn write_apng(frames: &[AnimationFrame], path: &Path) -> io::Result<()> {
+    let first = first_frame(frames)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), first.canvas.width, first.canvas.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(io::Error::other)?;
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for frame in frames {
+        writer.set_frame_delay(frame.frames as u16, FRAME_RATE as u16).map_err(io::Error::other)?;
+        writer.write_image_data(&frame.canvas.to_rgba8()).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+fn write_frame_sequence(frames: &[AnimationFrame], dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut index = 0;
+    for frame in frames {
+        for _ in 0..(frame.frames / DELAY).max(1) {
+            frame.canvas.save_png(&dir.join(format!("{:05}.png", index)))?;
+            index += 1;
+        }
+    }
+    println!("Wrote {index} frames; encode with: ffmpeg -framerate {} -i {}/%05d.png out.mp4", FRAME_RATE / DELAY, dir.display());
+    Ok(())
+}
+
+pub(crate) fn export_animation(trace: &Trace, path: &Path, options: &ExportOptions, decode: Option<Target>) -> io::Result<()> {
+    let res = decode.map(|target| describe_result(&trace.terms[trace.terms.len() - 1], target)).unwrap_or_default();
+    let frames = render_animation(trace, &res, options);
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("gif") => write_gif(&frames, path),
+        Some("png") | Some("apng") => write_apng(&frames, path),
+        _ => write_frame_sequence(&frames, path)
+    }
+}
@@ -33,7 +33,7 @@ impl Distribution<ExprType> for StandardUniform {
     }
 }
 
-fn decode_id(id: usize) -> String {
+pub(crate) fn decode_id(id: usize) -> String {
     format!("{}{}", (id % 1000) as u8 as char, if id < 1000 { "".to_string() } else { ((id - (id % 1000)) / 1000).to_string() })
 }
 
@@ -47,6 +47,12 @@ impl fmt::Display for LambdaExpr {
     }
 }
 
+impl LambdaExpr {
+    pub(crate) fn size(&self) -> usize {
+        1 + self.children.iter().map(|child| child.size()).sum::<usize>()
+    }
+}
+
 impl PartialEq for LambdaExpr {
     fn eq(&self, other: &Self) -> bool {
         if self.expr_type != other.expr_type {
@@ -1,23 +1,256 @@
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
+use arboard::Clipboard;
+use tokio::runtime::Handle;
 use speedy2d::Graphics2D;
+use speedy2d::dimen::Vec2;
+use speedy2d::shape::Rectangle;
 use speedy2d::color::Color;
 use speedy2d::font::{Font, TextAlignment, TextLayout, TextOptions};
-use speedy2d::window::{KeyScancode, VirtualKeyCode, WindowHandler, WindowHelper};
-use crate::diagrams::{construct_diagram, Direction, Passthrough};
+use speedy2d::window::{KeyScancode, ModifiersState, MouseButton, MouseScrollDistance, VirtualKeyCode, WindowHandler, WindowHelper};
+use crate::animation::transition_scene;
+use crate::diagrams::{build_diagram, Diagram, DiagramStyle, Direction, Line, LABEL_SIZE};
+use crate::backend::{parse_answer, Target, TranslationBackend};
+use crate::decoding::is_lambda_term;
 use crate::expr::LambdaExpr;
-use crate::numerals::unchurch;
-use crate::ollama::{handle_prompt, instantiate_ollama};
+use crate::jobs::{Job, JobEvent, JobInput};
+use crate::manual::ManualTrace;
+use crate::readback::describe_result;
+use crate::session::Session;
+use crate::structured::structured_to_arithmetic;
+use crate::prompt::{history_path, PromptEditor};
+use crate::reduction::{redex_around, ReductionOptions, Rule, StepInfo, Trace};
 use crate::sound::sound_thread;
 
-const LINE_THICKNESS: f32 = 5.0;
-const TEXT_SIZE: f32 = 13.0;
+pub(crate) const FONT_BYTES: &[u8] = include_bytes!("../IosevkaTermSlabNerdFont-Medium.ttf");
+pub(crate) const LINE_THICKNESS: f32 = 5.0;
+pub(crate) const TEXT_SIZE: f32 = 13.0;
 const TEXT_CUTOFF: usize = 3000;
-const TEXT_WIDTH: f32 = 300.0;
-const TEXT_PADDING: f32 = 100.0;
-const DELAY: u64 = 10;
-const DELAY_START_MULTIPLIER: u64 = 1;
+pub(crate) const TEXT_WIDTH: f32 = 300.0;
+pub(crate) const TEXT_PADDING: f32 = 100.0;
+pub(crate) const DELAY: u64 = 10;
+pub(crate) const DELAY_START_MULTIPLIER: u64 = 1;
+const MIN_LABEL_SIZE: f32 = 4.0;
+const ZOOM_STEP: f32 = 1.2;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 100.0;
+const PIXELS_PER_LINE: f32 = 40.0;
+const HIT_RADIUS: f32 = 6.0;
+const TIMELINE_MARGIN: f32 = 40.0;
+const TIMELINE_HEIGHT: f32 = 4.0;
+const TIMELINE_HIT: f32 = 12.0;
+const KNOB_RADIUS: f32 = 7.0;
+const MIN_DELAY: u64 = 1;
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAMES: u64 = 6;
+const MAX_DELAY: u64 = 240;
+const CLICK_SLOP: f32 = 4.0;
+const ERROR_COLOR: Color = Color::RED;
+const HINT_COLOR: Color = Color::GRAY;
+const TIMELINE_COLOR: Color = Color::DARK_GRAY;
+const ABSTRACTION_COLOR: Color = Color::RED;
+const ARGUMENT_COLOR: Color = Color::GREEN;
+
+pub(crate) fn source_color(source: &[usize], redexes: &[StepInfo], normal_form: bool) -> Color {
+    if normal_form {
+        return Color::BLUE;
+    }
+    for redex in redexes {
+        if !source.starts_with(&redex.path) {
+            continue;
+        }
+        match redex.rule {
+            Rule::Beta if source.len() > redex.path.len() => {
+                return if source[redex.path.len()] == 0 { ABSTRACTION_COLOR } else { ARGUMENT_COLOR };
+            }
+            Rule::Eta => return ABSTRACTION_COLOR,
+            _ => {}
+        }
+    }
+    Color::WHITE
+}
+
+pub(crate) fn line_color(line: &Line, redexes: &[StepInfo], normal_form: bool) -> Color {
+    source_color(&line.source, redexes, normal_form)
+}
+
+pub(crate) fn term_text(term: &LambdaExpr) -> String {
+    let string = term.to_string();
+    if string.len() <= TEXT_CUTOFF {
+        string
+    } else {
+        string.chars().take(TEXT_CUTOFF).collect::<String>() + "..."
+    }
+}
+
+pub(crate) fn diagram_area(win_size: (f32, f32)) -> (f32, f32) {
+    (win_size.0 - (TEXT_WIDTH + 2.0 * TEXT_PADDING), win_size.1)
+}
+
+pub(crate) struct ScreenLine {
+    pub(crate) start: (f32, f32),
+    pub(crate) end: (f32, f32),
+    pub(crate) thickness: f32,
+    pub(crate) color: Color
+}
+
+pub(crate) struct ScreenLabel {
+    pub(crate) position: (f32, f32),
+    pub(crate) text: String,
+    pub(crate) size: f32,
+    pub(crate) color: Color
+}
+
+pub(crate) struct Scene {
+    pub(crate) lines: Vec<ScreenLine>,
+    pub(crate) labels: Vec<ScreenLabel>
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct View {
+    pub(crate) zoom: f32,
+    pub(crate) pan: (f32, f32)
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: (0.0, 0.0)
+        }
+    }
+}
+
+impl View {
+    fn zoom_at(&mut self, cursor: (f32, f32), area: (f32, f32), factor: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let factor = zoom / self.zoom;
+        let center = (area.0 / 2.0, area.1 / 2.0);
+        self.pan.0 = cursor.0 - center.0 - (cursor.0 - center.0 - self.pan.0) * factor;
+        self.pan.1 = cursor.1 - center.1 - (cursor.1 - center.1 - self.pan.1) * factor;
+        self.zoom = zoom;
+    }
+}
+
+fn fit_transform(diagram: &Diagram, area: (f32, f32), view: &View) -> (f32, (f32, f32)) {
+    let right_edge = diagram.rightmost().0;
+    let bottom_edge = diagram.bottommost().1;
+    let x_scale = area.0 / right_edge;
+    let y_scale = area.1 / bottom_edge;
+    let scale = x_scale.min(y_scale).min(1.0);
+    let x_offset = (area.0 - (right_edge * scale)) / 2.0;
+    let y_offset = (area.1 - (bottom_edge * scale)) / 2.0;
+    let center = (area.0 / 2.0, area.1 / 2.0);
+    (scale * view.zoom, (
+        center.0 + (x_offset - center.0) * view.zoom + view.pan.0,
+        center.1 + (y_offset - center.1) * view.zoom + view.pan.1
+    ))
+}
+
+fn layout_lines(diagram: &Diagram, transform: (f32, (f32, f32)), redexes: &[StepInfo], normal_form: bool) -> Vec<ScreenLine> {
+    let (scale, (x_offset, y_offset)) = transform;
+    diagram.lines.iter().map(|line| {
+        let startpoint = match line.direction {
+            Direction::Vertical => (line.origin.0 * scale + x_offset, (line.origin.1 - LINE_THICKNESS / 2.0) * scale + y_offset),
+            Direction::Horizontal => (line.origin.0 * scale + x_offset, line.origin.1 * scale + y_offset)
+        };
+        let endpoint = match line.direction {
+            Direction::Vertical => (line.origin.0 * scale + x_offset, (line.origin.1 + line.length + LINE_THICKNESS / 2.0) * scale + y_offset),
+            Direction::Horizontal => ((line.origin.0 + line.length + LINE_THICKNESS / 2.0) * scale + x_offset, line.origin.1 * scale + y_offset)
+        };
+        ScreenLine {
+            start: startpoint,
+            end: endpoint,
+            thickness: LINE_THICKNESS * scale,
+            color: line_color(line, redexes, normal_form)
+        }
+    }).collect()
+}
+
+pub(crate) fn layout_scene(diagram: &Diagram, area: (f32, f32), view: &View, redexes: &[StepInfo], normal_form: bool) -> Scene {
+    let (scale, offset) = fit_transform(diagram, area, view);
+    let screen = |point: (f32, f32)| (point.0 * scale + offset.0, point.1 * scale + offset.1);
+    let mut lines = layout_lines(diagram, (scale, offset), redexes, normal_form);
+    for arrowhead in &diagram.arrowheads {
+        for (start, end) in arrowhead.strokes() {
+            lines.push(ScreenLine {
+                start: screen(start),
+                end: screen(end),
+                thickness: LINE_THICKNESS * scale / 2.0,
+                color: source_color(&arrowhead.source, redexes, normal_form)
+            });
+        }
+    }
+    let labels = diagram.labels.iter()
+        .filter(|_| LABEL_SIZE * scale >= MIN_LABEL_SIZE)
+        .map(|label| ScreenLabel {
+            position: screen(label.position),
+            text: label.text.clone(),
+            size: LABEL_SIZE * scale,
+            color: source_color(&label.source, redexes, normal_form)
+        })
+        .collect();
+    Scene { lines, labels }
+}
+
+fn segment_distance(point: (f32, f32), line: &ScreenLine) -> f32 {
+    let (dx, dy) = (line.end.0 - line.start.0, line.end.1 - line.start.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((point.0 - line.start.0) * dx + (point.1 - line.start.1) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    let (x, y) = (line.start.0 + t * dx - point.0, line.start.1 + t * dy - point.1);
+    (x * x + y * y).sqrt()
+}
+
+pub(crate) fn line_at<'a>(diagram: &'a Diagram, scene: &Scene, point: (f32, f32)) -> Option<&'a [usize]> {
+    diagram.lines.iter()
+        .zip(&scene.lines)
+        .map(|(line, screen_line)| (line, segment_distance(point, screen_line) - screen_line.thickness / 2.0))
+        .filter(|(_, distance)| *distance <= HIT_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(line, _)| line.source.as_slice())
+}
+
+fn draw_scene(graphics: &mut Graphics2D, font: &Font, scene: &Scene, area: (f32, f32)) {
+    graphics.set_clip(Some(Rectangle::from_tuples((0, 0), (area.0 as i32, area.1 as i32))));
+    for line in &scene.lines {
+        graphics.draw_line(line.start, line.end, line.thickness, line.color);
+    }
+    for label in &scene.labels {
+        let text = font.layout_text(&label.text, label.size, TextOptions::new());
+        graphics.draw_text((label.position.0 - text.width() / 2.0, label.position.1 - text.height() / 2.0), label.color, &text);
+    }
+    graphics.set_clip(None);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputMode {
+    Query,
+    Lambda,
+    Term
+}
+
+impl InputMode {
+    fn next(self) -> Self {
+        match self {
+            InputMode::Query => InputMode::Lambda,
+            InputMode::Lambda => InputMode::Term,
+            InputMode::Term => InputMode::Query
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            InputMode::Query => "Ask a question, or press Tab to have it answered with a lambda term",
+            InputMode::Lambda => "Ask for a lambda term built from I K S Y pair iszero ..., or press Tab to type one yourself",
+            InputMode::Term => "Type a term (\\x.x or λx.x) or arithmetic like 3 + 4 * 2, or press Tab to ask a question"
+        }
+    }
+}
 
 pub(crate) struct LambdaGraphicsHandler {
     pub(crate) terms: Vec<LambdaExpr>,
@@ -26,6 +259,7 @@ pub(crate) struct LambdaGraphicsHandler {
     res_cmp: String,
     delay: u64,
     original_terms: Vec<LambdaExpr>,
+    original_steps: Vec<Vec<StepInfo>>,
     original_res: String,
     first_frame: bool,
     played_sound: bool,
@@ -33,11 +267,37 @@ pub(crate) struct LambdaGraphicsHandler {
     trigger_flag: Arc<Mutex<bool>>,
     frames_to_render: i64,
     frame: u64,
-    prompt: String
+    prompt: PromptEditor,
+    modifiers: ModifiersState,
+    options: ReductionOptions,
+    animate: bool,
+    shown: Option<(LambdaExpr, Vec<StepInfo>)>,
+    shown_text: String,
+    style: DiagramStyle,
+    stale: bool,
+    view: View,
+    cursor: (f32, f32),
+    drag: Option<(f32, f32)>,
+    pressed_at: Option<(f32, f32)>,
+    manual: Option<ManualTrace>,
+    hover: Option<Vec<usize>>,
+    input_mode: InputMode,
+    input_error: Option<String>,
+    step: usize,
+    scrubbing: bool,
+    runtime: Handle,
+    backend: Arc<dyn TranslationBackend>,
+    job: Option<Job>,
+    decode: Option<Target>,
+    session: Session,
+    pending: Option<(String, String)>,
+    /// The prompt holds the model's answer to `pending`, to be checked before it is reduced.
+    reviewing: bool,
+    ticks: u64
 }
 
 impl LambdaGraphicsHandler {
-    pub(crate) fn new(font: Font) -> Self {
+    pub(crate) fn new(font: Font, options: ReductionOptions, style: DiagramStyle, backend: Arc<dyn TranslationBackend>) -> Self {
         Self {
             terms: vec![],
             font,
@@ -45,6 +305,7 @@ impl LambdaGraphicsHandler {
             res_cmp: String::new(),
             delay: DELAY * DELAY_START_MULTIPLIER,
             original_terms: vec![],
+            original_steps: vec![],
             original_res: String::new(),
             first_frame: true,
             played_sound: false,
@@ -52,29 +313,367 @@ impl LambdaGraphicsHandler {
             trigger_flag: sound_thread(),
             frames_to_render: -1,
             frame: 1,
-            prompt: String::new()
+            prompt: PromptEditor::new(history_path()),
+            modifiers: ModifiersState::default(),
+            options,
+            animate: false,
+            shown: None,
+            shown_text: String::new(),
+            style,
+            stale: false,
+            view: View::default(),
+            cursor: (0.0, 0.0),
+            drag: None,
+            pressed_at: None,
+            manual: None,
+            hover: None,
+            input_mode: InputMode::Query,
+            input_error: None,
+            step: 0,
+            scrubbing: false,
+            runtime: Handle::current(),
+            backend,
+            job: None,
+            decode: Some(Target::Arithmetic),
+            session: Session::default(),
+            pending: None,
+            reviewing: false,
+            ticks: 0
+        }
+    }
+
+    pub(crate) fn report(&mut self, error: String) {
+        self.input_error = Some(error);
+    }
+
+    fn draw_text_panel(&self, graphics: &mut Graphics2D, win_size: (f32, f32)) {
+        let text_options: TextOptions = TextOptions::new().with_wrap_to_width(TEXT_WIDTH, TextAlignment::Left);
+        let text = self.font.layout_text(self.shown_text.as_str(), TEXT_SIZE, text_options);
+        graphics.draw_text((win_size.0 - (TEXT_WIDTH + TEXT_PADDING), TEXT_PADDING), Color::WHITE, &text);
+    }
+
+    fn draw_prompt(&self, graphics: &mut Graphics2D, win_size: (f32, f32)) {
+        let mut position = (win_size.0 / 2.0 - (TEXT_WIDTH / 2.0), win_size.1 / 2.0);
+        let hint = match &self.pending {
+            Some((question, _)) if self.reviewing => {
+                let kind = if self.decode == Some(Target::Lambda) { "lambda term" } else { "arithmetic" };
+                format!("\"{question}\" was read as this {kind}. Fix it if needed, then Return to reduce, or Esc to change the question")
+            }
+            _ => self.input_mode.hint().to_string()
+        };
+        let mut lines = vec![(self.prompt.with_caret(), Color::WHITE), (hint, HINT_COLOR)];
+        if let Some(summary) = self.session.summary() {
+            lines.push((format!("{summary} · Tab on a result starts over"), HINT_COLOR));
+        }
+        if let Some(error) = &self.input_error {
+            lines.push((error.clone(), ERROR_COLOR));
+        }
+        if self.job.is_some() {
+            lines.push((format!("{} translating, Esc to cancel", self.spinner()), HINT_COLOR));
+        }
+        for (line, color) in lines {
+            let text_options: TextOptions = TextOptions::new().with_wrap_to_width(TEXT_WIDTH, TextAlignment::Center);
+            let text = self.font.layout_text(&line, TEXT_SIZE, text_options);
+            graphics.draw_text(position, color, &text);
+            position.1 += text.height().max(TEXT_SIZE) + TEXT_SIZE;
+        }
+    }
+
+    fn paste(&mut self) {
+        match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => self.prompt.insert_str(&text),
+            Err(e) => self.input_error = Some(format!("Could not paste: {e}"))
+        }
+    }
+
+    fn load_trace(&mut self, trace: Trace, res: String) {
+        self.original_terms = trace.terms;
+        self.original_steps = trace.steps;
+        self.terms = self.original_terms.clone();
+        self.res = res.clone();
+        self.original_res = res;
+        self.prompt.clear();
+        *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
+    }
+
+    fn spinner(&self) -> char {
+        SPINNER[(self.ticks / SPINNER_FRAMES) as usize % SPINNER.len()]
+    }
+
+    fn submit_prompt(&mut self) {
+        if self.job.is_some() {
+            return;
+        }
+        if self.reviewing {
+            self.confirm_answer();
+            return;
+        }
+        self.prompt.commit();
+        let input = self.prompt.text();
+        let job_input = match self.input_mode {
+            InputMode::Query => JobInput::Query(input.clone(), Target::Arithmetic, self.session.clone()),
+            InputMode::Lambda => JobInput::Query(input.clone(), Target::Lambda, self.session.clone()),
+            InputMode::Term => match self.session.parse_expression(&input, false) {
+                Ok(term) => JobInput::Term(term),
+                Err(e) => {
+                    self.input_error = Some(e);
+                    return;
+                }
+            }
+        };
+        self.decode = match self.input_mode {
+            InputMode::Query => Some(Target::Arithmetic),
+            InputMode::Lambda => Some(Target::Lambda),
+            InputMode::Term => (!is_lambda_term(&input)).then_some(Target::Arithmetic)
+        };
+        let answer = if self.input_mode == InputMode::Term { input.clone() } else { String::new() };
+        self.pending = Some((input, answer));
+        self.job = Some(Job::spawn(&self.runtime, job_input, self.options.clone(), Arc::clone(&self.backend)));
+    }
+
+    /// Reduces the answer under review, as edited.
+    fn confirm_answer(&mut self) {
+        let answer = self.prompt.text();
+        let target = self.decode.unwrap_or(Target::Arithmetic);
+        let term = match parse_answer(&answer, target, &self.session) {
+            Ok(x) => x,
+            Err(e) => {
+                self.input_error = Some(format!("Could not read \"{}\": {e}", answer.trim()));
+                return;
+            }
+        };
+        if let Some(pending) = &mut self.pending {
+            pending.1 = answer.trim().to_string();
+        }
+        self.reviewing = false;
+        self.job = Some(Job::spawn(&self.runtime, JobInput::Term(term), self.options.clone(), Arc::clone(&self.backend)));
+    }
+
+    /// Leaves the answer under review and puts the question back in the prompt.
+    fn back_to_question(&mut self) {
+        if let Some((question, _)) = self.pending.take() {
+            self.prompt.replace(&question);
+        }
+        self.reviewing = false;
+    }
+
+    fn poll_job(&mut self) {
+        let events = match &self.job {
+            Some(job) => job.poll(),
+            None => return
+        };
+        for event in events {
+            match event {
+                JobEvent::Translated(answer) => {
+                    self.prompt.replace(&structured_to_arithmetic(&answer).unwrap_or(answer));
+                    self.reviewing = true;
+                    self.job = None;
+                }
+                JobEvent::Started(term) => self.load_trace(Trace { terms: vec![term], steps: vec![] }, String::new()),
+                JobEvent::Step(term, steps) => {
+                    self.original_terms.push(term.clone());
+                    self.original_steps.push(steps);
+                    self.terms.push(term);
+                }
+                JobEvent::Finished => {
+                    let normal_form = &self.original_terms[self.original_terms.len() - 1];
+                    let res = self.decode.map(|target| describe_result(normal_form, target)).unwrap_or_default();
+                    if let Some((prompt, answer)) = self.pending.take() {
+                        self.session.record(prompt, answer, normal_form.clone(), self.decode.unwrap_or(Target::Lambda));
+                    }
+                    self.res = res.clone();
+                    self.original_res = res;
+                    self.job = None;
+                }
+                JobEvent::Failed(e) => {
+                    self.input_error = Some(e);
+                    self.pending = None;
+                    self.job = None;
+                }
+            }
+        }
+    }
+
+    /// Returns to the prompt, keeping the session for follow-up questions.
+    fn clear_scene(&mut self) {
+        if self.terms.len() > 1 {
+            *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
+            sleep(Duration::from_millis(20));
+            *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
+        }
+        self.job = None;
+        self.pending = None;
+        self.reviewing = false;
+        self.terms = vec![];
+        self.original_terms = vec![];
+        self.original_steps = vec![];
+        self.res = String::new();
+        self.res_cmp = String::new();
+        self.prompt.clear();
+        self.shown = None;
+        self.manual = None;
+        self.hover = None;
+        self.frame = 0;
+        self.played_sound = false;
+        self.first_frame = true;
+        self.frames_to_render = -1;
+    }
+
+    fn cancel_job(&mut self) {
+        if self.job.take().is_some() && !self.original_terms.is_empty() {
+            self.res = " (cancelled)".to_string();
+            self.original_res = self.res.clone();
+            self.stale = true;
+        }
+    }
+
+    fn timeline(&self, win_size: (f32, f32)) -> (f32, f32, f32) {
+        let area = diagram_area(win_size);
+        (TIMELINE_MARGIN, area.0 - TIMELINE_MARGIN, area.1 - TIMELINE_MARGIN)
+    }
+
+    fn on_timeline(&self, win_size: (f32, f32)) -> bool {
+        let (start, end, y) = self.timeline(win_size);
+        self.original_terms.len() > 1
+            && self.cursor.0 >= start - TIMELINE_HIT
+            && self.cursor.0 <= end + TIMELINE_HIT
+            && (self.cursor.1 - y).abs() <= TIMELINE_HIT
+    }
+
+    fn scrub(&mut self, win_size: (f32, f32)) {
+        let (start, end, _) = self.timeline(win_size);
+        let fraction = ((self.cursor.0 - start) / (end - start)).clamp(0.0, 1.0);
+        let step = (fraction * (self.original_terms.len() - 1) as f32).round() as usize;
+        if step != self.step {
+            self.jump_to(step);
         }
     }
+
+    fn jump_to(&mut self, step: usize) {
+        self.terms = self.original_terms[step.min(self.original_terms.len() - 1)..].to_vec();
+        self.res = self.original_res.clone();
+        self.res_cmp = String::new();
+        self.first_frame = false;
+        if self.frames_to_render <= 0 {
+            self.frames_to_render = 1;
+        }
+        self.frame = 0;
+    }
+
+    fn draw_overlay(&self, graphics: &mut Graphics2D, win_size: (f32, f32)) {
+        let steps = self.original_terms.len().saturating_sub(1);
+        let size = self.shown.as_ref().map(|(term, _)| term.size()).unwrap_or(0);
+        let (start, end, y) = self.timeline(win_size);
+        let mut status = format!("step {}/{}  ·  {}  ·  size {}  ·  {} frames per step", self.step, steps, self.options.describe(), size, self.delay);
+        if self.job.is_some() {
+            status += &format!("  ·  {} reducing, Esc to cancel", self.spinner());
+        }
+        let text = self.font.layout_text(&status, TEXT_SIZE, TextOptions::new());
+        graphics.draw_text((start, y - TIMELINE_HIT - text.height()), HINT_COLOR, &text);
+        graphics.draw_rectangle(Rectangle::from_tuples((start, y - TIMELINE_HEIGHT / 2.0), (end, y + TIMELINE_HEIGHT / 2.0)), TIMELINE_COLOR);
+        let knob = if steps == 0 { end } else { start + (end - start) * self.step as f32 / steps as f32 };
+        graphics.draw_rectangle(Rectangle::from_tuples((start, y - TIMELINE_HEIGHT / 2.0), (knob, y + TIMELINE_HEIGHT / 2.0)), Color::WHITE);
+        graphics.draw_circle((knob, y), KNOB_RADIUS, Color::WHITE);
+    }
+
+    fn manual_text(&self, manual: &ManualTrace) -> String {
+        let mut text = format!("manual step {}\n{}", manual.steps(), term_text(manual.current()));
+        if manual.is_normal_form() {
+            let res = self.decode.map(|target| describe_result(manual.current(), target)).unwrap_or_default();
+            text += &format!("{res}\nnormal form after {} steps (automatic: {})", manual.steps(), self.original_steps.len());
+        }
+        text
+    }
+
+    fn manual_scene(&self, manual: &ManualTrace, area: (f32, f32)) -> (Diagram, Scene) {
+        let diagram = build_diagram(manual.current(), self.style);
+        let redexes: Vec<StepInfo> = self.hover.iter()
+            .map(|path| StepInfo {
+                rule: Rule::Beta,
+                path: path.clone(),
+                var: 0,
+                arg: None,
+                size_delta: 0
+            })
+            .collect();
+        let scene = layout_scene(&diagram, area, &self.view, &redexes, manual.is_normal_form());
+        (diagram, scene)
+    }
+
+    fn update_hover(&mut self, win_size: (f32, f32)) {
+        let manual = match &self.manual {
+            Some(x) => x,
+            None => return
+        };
+        let (diagram, scene) = self.manual_scene(manual, diagram_area(win_size));
+        self.hover = line_at(&diagram, &scene, self.cursor).and_then(|source| redex_around(manual.current(), source));
+    }
+
+    fn draw_manual(&mut self, graphics: &mut Graphics2D, win_size: (f32, f32)) {
+        let manual = match &self.manual {
+            Some(x) => x,
+            None => return
+        };
+        graphics.clear_screen(Color::BLACK);
+        let area = diagram_area(win_size);
+        draw_scene(graphics, &self.font, &self.manual_scene(manual, area).1, area);
+        self.shown_text = self.manual_text(manual);
+        self.draw_text_panel(graphics, win_size);
+    }
+
+    fn draw_transition(&self, graphics: &mut Graphics2D, win_size: (f32, f32), still: bool) {
+        let (term, redexes) = match &self.shown {
+            Some(x) => x,
+            None => return
+        };
+        graphics.clear_screen(Color::BLACK);
+        let area = diagram_area(win_size);
+        let scene = if still || redexes.is_empty() || self.terms.is_empty() {
+            layout_scene(&build_diagram(term, self.style), area, &self.view, redexes, redexes.is_empty() && self.terms.len() <= 1)
+        } else {
+            let t = (self.frame % self.delay) as f32 / self.delay as f32;
+            transition_scene(term, &self.terms[0], redexes, area, &self.view, t, self.style)
+        };
+        draw_scene(graphics, &self.font, &scene, area);
+        self.draw_text_panel(graphics, win_size);
+        self.draw_overlay(graphics, win_size);
+    }
 }
 
 impl WindowHandler for LambdaGraphicsHandler {
     fn on_draw(&mut self, helper: &mut WindowHelper<()>, graphics: &mut Graphics2D) {
         let win_size = helper.get_size_pixels();
+        self.ticks += 1;
+        self.poll_job();
         if self.terms.len() == 0 {
             graphics.clear_screen(Color::BLACK);
-            let text_options: TextOptions = TextOptions::new().with_wrap_to_width(TEXT_WIDTH, TextAlignment::Center);
-            let text = self.font.layout_text(self.prompt.as_str(), TEXT_SIZE, text_options);
-            graphics.draw_text((win_size.x as f32 / 2.0 - (TEXT_WIDTH / 2.0), win_size.y as f32 / 2.0), Color::WHITE, &text);
+            self.draw_prompt(graphics, (win_size.x as f32, win_size.y as f32));
+            helper.request_redraw();
+            return;
+        }
+        if self.manual.is_some() {
+            if self.stale {
+                self.stale = false;
+                self.draw_manual(graphics, (win_size.x as f32, win_size.y as f32));
+            }
             helper.request_redraw();
             return;
         }
         if self.frames_to_render == 0 {
+            if self.stale {
+                self.stale = false;
+                self.draw_transition(graphics, (win_size.x as f32, win_size.y as f32), true);
+            }
             helper.request_redraw();
             return;
         }
         self.frames_to_render -= 1;
         if self.frame % self.delay != 0 {
             self.frame += 1;
+            if self.animate || self.stale {
+                self.stale = false;
+                self.draw_transition(graphics, (win_size.x as f32, win_size.y as f32), !self.animate);
+            }
             helper.request_redraw();
             return;
         }
@@ -90,54 +689,34 @@ impl WindowHandler for LambdaGraphicsHandler {
         } else {
             (self.terms[0].clone(), false)
         };
-        let diagram = construct_diagram(&term, &Passthrough::top());
-        let right_edge = diagram.rightmost().0;
-        let bottom_edge = diagram.bottommost().1;
-        let x_scale = (win_size.x as f32 - (TEXT_WIDTH + 2.0 * TEXT_PADDING)) / right_edge;
-        let y_scale = win_size.y as f32 / bottom_edge;
-        let scale = x_scale.min(y_scale).min(1.0);
-        let x_offset = (win_size.x as f32 - (TEXT_WIDTH + 2.0 * TEXT_PADDING) - (right_edge * scale)) / 2.0;
-        let y_offset = (win_size.y as f32 - (bottom_edge * scale)) / 2.0;
-        for line in diagram.lines {
-            let startpoint = match line.direction {
-                Direction::Vertical => (line.origin.0 * scale + x_offset, (line.origin.1 - (LINE_THICKNESS * scale) / 2.0) * scale + y_offset),
-                Direction::Horizontal => (line.origin.0 * scale + x_offset, line.origin.1 * scale + y_offset)
-            };
-            let endpoint = match line.direction {
-                Direction::Vertical => (line.origin.0 * scale + x_offset, (line.origin.1 + line.length + (LINE_THICKNESS * scale) / 2.0) * scale + y_offset),
-                Direction::Horizontal => ((line.origin.0 + line.length + (LINE_THICKNESS * scale) / 2.0) * scale + x_offset, line.origin.1 * scale + y_offset)
-            };
-            graphics.draw_line(startpoint, endpoint, LINE_THICKNESS * scale, if removed { Color::WHITE } else { Color::BLUE });
-        }
-        if !removed && !self.played_sound {
+        let redexes: Vec<StepInfo> = if removed {
+            self.original_steps[self.original_terms.len() - self.terms.len() - 1].clone()
+        } else {
+            vec![]
+        };
+        let final_term = !removed && self.job.is_none();
+        let area = diagram_area((win_size.x as f32, win_size.y as f32));
+        draw_scene(graphics, &self.font, &layout_scene(&build_diagram(&term, self.style), area, &self.view, &redexes, final_term), area);
+        if final_term && !self.played_sound {
             self.play_next_frame = true;
             self.played_sound = true;
         } else if self.play_next_frame {
             *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
             self.play_next_frame = false;
         }
-        let term_string: String = if removed {
-            let string = term.to_string();
-            if string.len() <= TEXT_CUTOFF {
-                string
-            } else {
-                string.chars().take(TEXT_CUTOFF).collect::<String>() + "..."
-            }
+        let term_string: String = if !final_term {
+            term_text(&term)
         } else {
             if self.res.len() > 0 {
                 self.res_cmp.push(self.res.remove(0));
             }
-            let string = term.to_string();
-            (if string.len() <= TEXT_CUTOFF {
-                string
-            } else {
-                string.chars().take(TEXT_CUTOFF).collect::<String>() + "..."
-            }) + self.res_cmp.as_str()
+            term_text(&term) + self.res_cmp.as_str()
         };
-        let term_str = term_string.as_str();
-        let text_options: TextOptions = TextOptions::new().with_wrap_to_width(TEXT_WIDTH, TextAlignment::Left);
-        let text = self.font.layout_text(term_str, TEXT_SIZE, text_options);
-        graphics.draw_text((win_size.x as f32 - (TEXT_WIDTH + TEXT_PADDING), TEXT_PADDING), Color::WHITE, &text);
+        self.shown_text = term_string;
+        self.shown = Some((term, redexes));
+        self.step = self.original_terms.len() - self.terms.len() - removed as usize;
+        self.draw_text_panel(graphics, (win_size.x as f32, win_size.y as f32));
+        self.draw_overlay(graphics, (win_size.x as f32, win_size.y as f32));
         helper.request_redraw();
     }
 
@@ -146,19 +725,68 @@ impl WindowHandler for LambdaGraphicsHandler {
             Some(x) => x,
             None => { return }
         };
+        if key_code == VirtualKeyCode::Escape {
+            if self.job.is_some() {
+                self.cancel_job();
+            } else if !self.original_terms.is_empty() {
+                self.clear_scene();
+            } else if self.reviewing {
+                self.back_to_question();
+            }
+            helper.request_redraw();
+            return;
+        }
         if self.original_terms.len() == 0 {
-            if key_code == VirtualKeyCode::Return {
-                let mut ollama = instantiate_ollama();
-                self.original_terms = futures::executor::block_on(handle_prompt(self.prompt.clone(), &mut ollama));
-                self.terms = self.original_terms.clone();
-                self.res = format!(" = {}", unchurch(&self.terms[self.terms.len() - 1]));
-                self.prompt = String::new();
-                *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
-            } else if key_code == VirtualKeyCode::Backspace {
-                self.prompt.pop();
+            let word = self.modifiers.ctrl() || self.modifiers.alt();
+            if key_code != VirtualKeyCode::Return {
+                self.input_error = None;
+            }
+            match key_code {
+                VirtualKeyCode::Return => self.submit_prompt(),
+                VirtualKeyCode::Backspace if word => self.prompt.delete_word(),
+                VirtualKeyCode::Backspace => self.prompt.backspace(),
+                VirtualKeyCode::W if self.modifiers.ctrl() => self.prompt.delete_word(),
+                VirtualKeyCode::Delete => self.prompt.delete(),
+                VirtualKeyCode::Left => self.prompt.left(word),
+                VirtualKeyCode::Right => self.prompt.right(word),
+                VirtualKeyCode::Home => self.prompt.home(),
+                VirtualKeyCode::End => self.prompt.end(),
+                VirtualKeyCode::Up => self.prompt.history_previous(),
+                VirtualKeyCode::Down => self.prompt.history_next(),
+                VirtualKeyCode::Tab if !self.reviewing => self.input_mode = self.input_mode.next(),
+                VirtualKeyCode::V if self.modifiers.ctrl() => self.paste(),
+                VirtualKeyCode::Paste => self.paste(),
+                _ => {}
             }
             return;
         }
+        if key_code == VirtualKeyCode::M {
+            self.manual = match self.manual {
+                Some(_) => None,
+                None => Some(ManualTrace::new(self.original_terms[0].clone()))
+            };
+            self.hover = None;
+            self.stale = true;
+            helper.request_redraw();
+            return;
+        }
+        if let Some(manual) = &mut self.manual {
+            let changed = match key_code {
+                VirtualKeyCode::Z | VirtualKeyCode::Left => manual.undo(),
+                VirtualKeyCode::Y | VirtualKeyCode::Right => manual.redo(),
+                _ => false
+            };
+            if changed {
+                let win_size = helper.get_size_pixels();
+                self.update_hover((win_size.x as f32, win_size.y as f32));
+                self.stale = true;
+                helper.request_redraw();
+                return;
+            }
+            if matches!(key_code, VirtualKeyCode::Return | VirtualKeyCode::Space | VirtualKeyCode::Left | VirtualKeyCode::Right) {
+                return;
+            }
+        }
         if key_code == VirtualKeyCode::Return {
             if self.terms.len() == 1 {
                 *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
@@ -166,7 +794,6 @@ impl WindowHandler for LambdaGraphicsHandler {
             self.terms = self.original_terms.clone();
             self.res = self.original_res.clone();
             self.res_cmp = String::new();
-            self.delay = DELAY * DELAY_START_MULTIPLIER;
             self.played_sound = false;
             self.first_frame = true;
             self.frames_to_render = -1;
@@ -181,7 +808,7 @@ impl WindowHandler for LambdaGraphicsHandler {
             self.frame = 0;
             helper.request_redraw();
         } else if key_code == VirtualKeyCode::Left {
-            if self.original_terms.len() - 2 < self.terms.len() {
+            if self.original_terms.len() < 2 || self.original_terms.len() - 2 < self.terms.len() {
                 return;
             }
             self.terms.insert(0, self.original_terms[self.original_terms.len() - self.terms.len() - 1].clone());
@@ -200,21 +827,31 @@ impl WindowHandler for LambdaGraphicsHandler {
             }
             self.frame = 0;
             helper.request_redraw();
+        } else if matches!(key_code, VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd) {
+            self.delay = (self.delay / 2).max(MIN_DELAY);
+            self.stale = true;
+        } else if matches!(key_code, VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract) {
+            self.delay = (self.delay * 2).min(MAX_DELAY);
+            self.stale = true;
+        } else if key_code == VirtualKeyCode::Home {
+            self.jump_to(0);
+            helper.request_redraw();
+        } else if key_code == VirtualKeyCode::End {
+            self.jump_to(self.original_terms.len() - 1);
+            helper.request_redraw();
+        } else if key_code == VirtualKeyCode::A {
+            self.animate = !self.animate;
+        } else if key_code == VirtualKeyCode::D {
+            self.style = self.style.next();
+            self.stale = true;
+            helper.request_redraw();
+        } else if key_code == VirtualKeyCode::F {
+            self.view = View::default();
+            self.stale = true;
+            helper.request_redraw();
         } else if key_code == VirtualKeyCode::Tab {
-            if self.terms.len() > 1 {
-                *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
-                sleep(Duration::from_millis(20));
-                *Arc::clone(&self.trigger_flag).lock().unwrap() = true;
-            }
-            self.terms = vec![];
-            self.original_terms = vec![];
-            self.res = String::new();
-            self.res_cmp = String::new();
-            self.prompt = String::new();
-            self.frame = 0;
-            self.played_sound = false;
-            self.first_frame = true;
-            self.frames_to_render = -1;
+            self.clear_scene();
+            self.session.clear();
             helper.request_redraw();
         }
     }
@@ -224,7 +861,84 @@ impl WindowHandler for LambdaGraphicsHandler {
             return;
         }
         if self.original_terms.len() == 0 {
-            self.prompt.push(unicode_codepoint);
+            self.prompt.insert(unicode_codepoint);
+            self.input_error = None;
+        }
+    }
+
+    fn on_keyboard_modifiers_changed(&mut self, _: &mut WindowHelper<()>, state: ModifiersState) {
+        self.modifiers = state;
+    }
+
+    fn on_mouse_move(&mut self, helper: &mut WindowHelper<()>, position: Vec2) {
+        self.cursor = (position.x, position.y);
+        if self.scrubbing {
+            let win_size = helper.get_size_pixels();
+            self.scrub((win_size.x as f32, win_size.y as f32));
+            helper.request_redraw();
+        } else if let Some(last) = self.drag {
+            self.view.pan.0 += position.x - last.0;
+            self.view.pan.1 += position.y - last.1;
+            self.drag = Some(self.cursor);
+            self.stale = true;
+            helper.request_redraw();
+        } else if self.manual.is_some() {
+            let hover = self.hover.clone();
+            let win_size = helper.get_size_pixels();
+            self.update_hover((win_size.x as f32, win_size.y as f32));
+            if self.hover != hover {
+                self.stale = true;
+                helper.request_redraw();
+            }
         }
     }
+
+    fn on_mouse_button_down(&mut self, helper: &mut WindowHelper<()>, button: MouseButton) {
+        if button != MouseButton::Left {
+            return;
+        }
+        let win_size = helper.get_size_pixels();
+        let win_size = (win_size.x as f32, win_size.y as f32);
+        if self.manual.is_none() && self.on_timeline(win_size) {
+            self.scrubbing = true;
+            self.scrub(win_size);
+            helper.request_redraw();
+        } else {
+            self.drag = Some(self.cursor);
+            self.pressed_at = Some(self.cursor);
+        }
+    }
+
+    fn on_mouse_button_up(&mut self, helper: &mut WindowHelper<()>, button: MouseButton) {
+        if button != MouseButton::Left {
+            return;
+        }
+        self.drag = None;
+        self.scrubbing = false;
+        let clicked = self.pressed_at.take()
+            .is_some_and(|(x, y)| (x - self.cursor.0).abs() <= CLICK_SLOP && (y - self.cursor.1).abs() <= CLICK_SLOP);
+        let (manual, hover) = match (&mut self.manual, &self.hover) {
+            (Some(manual), Some(hover)) if clicked => (manual, hover),
+            _ => return
+        };
+        if manual.reduce_at(hover) {
+            let win_size = helper.get_size_pixels();
+            self.update_hover((win_size.x as f32, win_size.y as f32));
+            self.stale = true;
+            helper.request_redraw();
+        }
+    }
+
+    fn on_mouse_wheel_scroll(&mut self, helper: &mut WindowHelper<()>, distance: MouseScrollDistance) {
+        let lines = match distance {
+            MouseScrollDistance::Lines { y, .. } => y as f32,
+            MouseScrollDistance::Pixels { y, .. } => y as f32 / PIXELS_PER_LINE,
+            MouseScrollDistance::Pages { y, .. } => y as f32
+        };
+        let win_size = helper.get_size_pixels();
+        let area = diagram_area((win_size.x as f32, win_size.y as f32));
+        self.view.zoom_at(self.cursor, area, ZOOM_STEP.powf(lines));
+        self.stale = true;
+        helper.request_redraw();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use tokio::runtime::Handle;
+use crate::backend::{interpret_query, Target, TranslationBackend};
+use crate::expr::LambdaExpr;
+use crate::reduction::{reduce_step, ReductionOptions, StepInfo};
+use crate::session::Session;
+
+pub(crate) enum JobInput {
+    /// Only translated: the answer comes back as [`JobEvent::Translated`] to be checked before it is reduced.
+    Query(String, Target, Session),
+    Term(LambdaExpr)
+}
+
+pub(crate) enum JobEvent {
+    Translated(String),
+    Started(LambdaExpr),
+    Step(LambdaExpr, Vec<StepInfo>),
+    Finished,
+    Failed(String)
+}
+
+pub(crate) struct Job {
+    events: Receiver<JobEvent>,
+    cancelled: Arc<AtomicBool>
+}
+
+fn stream_reduction(mut expr: LambdaExpr, options: &ReductionOptions, sender: &Sender<JobEvent>, cancelled: &AtomicBool) {
+    if sender.send(JobEvent::Started(expr.clone())).is_err() {
+        return;
+    }
+    while !cancelled.load(Ordering::Relaxed) {
+        let steps = reduce_step(&mut expr, options);
+        if steps.is_empty() {
+            let _ = sender.send(JobEvent::Finished);
+            return;
+        }
+        if sender.send(JobEvent::Step(expr.clone(), steps)).is_err() {
+            return;
+        }
+    }
+}
+
+impl Job {
+    pub(crate) fn spawn(runtime: &Handle, input: JobInput, options: ReductionOptions, backend: Arc<dyn TranslationBackend>) -> Self {
+        let (sender, events) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        runtime.spawn(async move {
+            let term = match input {
+                JobInput::Term(term) => term,
+                JobInput::Query(prompt, target, session) => {
+                    let event = match interpret_query(&prompt, target, &session, backend.as_ref()).await {
+                        Ok((_, answer)) => JobEvent::Translated(answer),
+                        Err(e) => JobEvent::Failed(e)
+                    };
+                    let _ = sender.send(event);
+                    return;
+                }
+            };
+            if flag.load(Ordering::Relaxed) {
+                return;
+            }
+            let _ = tokio::task::spawn_blocking(move || stream_reduction(term, &options, &sender, &flag)).await;
+        });
+        Self {
+            events,
+            cancelled
+        }
+    }
+
+    pub(crate) fn poll(&self) -> Vec<JobEvent> {
+        self.events.try_iter().collect()
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
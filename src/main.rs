@@ -6,13 +6,146 @@ mod numerals;
 mod graphics;
 mod diagrams;
 mod sound;
+mod cli;
+mod bench;
+mod eta;
+mod animation;
+mod svg;
+mod export;
+mod raster;
+mod text_diagram;
+mod tree_diagrams;
+mod manual;
+mod prompt;
+mod jobs;
+mod backend;
+mod mock_server;
+mod config;
+mod structured;
+mod prelude;
+mod readback;
+mod session;
+mod english;
+mod cache;
+use std::sync::Arc;
 use speedy2d::font::Font;
 use speedy2d::window::WindowCreationOptions;
-use crate::graphics::LambdaGraphicsHandler;
+use crate::bench::run_benchmark;
+use crate::backend::{backend_from_spec, MockBackend, Target};
+use crate::cli::{load_input, parse_args, read_session_input, result_target, Command};
+use crate::readback::describe_result;
+use crate::session::Session;
+use crate::export::export_animation;
+use crate::graphics::{LambdaGraphicsHandler, FONT_BYTES};
+use crate::raster::{export_png, export_trace_png, render_filmstrip};
+use crate::reduction::{reduce_trace, EtaRule};
+use crate::eta::{eta_long, eta_normal_form};
+use crate::svg::{export_svg, export_trace_svg};
+use crate::text_diagram::{diagram_to_text, trace_to_text};
+use crate::diagrams::build_diagram;
+use crate::mock_server::serve;
+use crate::config::load_config;
+use crate::cache::clear_cache;
+
+/// Exit status for a bad flag, config file or backend.
+const USAGE_ERROR: i32 = 2;
+/// Exit status for input that could not be read or translated.
+const INPUT_ERROR: i32 = 1;
+
+/// Unwraps `result`, or prints its error and exits with `code` rather than panicking.
+fn exit_with<T>(code: i32, result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(code)
+    })
+}
 
 #[tokio::main]
 async fn main() {
-    let options = WindowCreationOptions::new_fullscreen_borderless();
-    let window = speedy2d::Window::new_with_options("Lambda", options).unwrap();
-    window.run_loop(LambdaGraphicsHandler::new(Font::new(include_bytes!("../IosevkaTermSlabNerdFont-Medium.ttf")).unwrap()));
-}
\ No newline at end of file
+    let args = exit_with(USAGE_ERROR, parse_args(std::env::args().skip(1)));
+    let config = exit_with(USAGE_ERROR, load_config(args.config_path.as_deref(), args.config));
+    if let Some(path) = config.cache.as_ref().filter(|_| args.clear_cache) {
+        exit_with(USAGE_ERROR, clear_cache(path));
+    }
+    let model = config.model.clone();
+    let backend = exit_with(USAGE_ERROR, backend_from_spec(&args.backend, config));
+    match args.command {
+        Command::Bench => run_benchmark(args.reduction.workers),
+        Command::Svg(input, output) => {
+            let term = exit_with(INPUT_ERROR, load_input(&input, args.target, backend.as_ref()).await);
+            if args.trace {
+                export_trace_svg(&reduce_trace(term, &args.reduction), &output, &args.export).unwrap();
+            } else {
+                export_svg(&term, &output, &args.export).unwrap();
+            }
+        }
+        Command::Png(input, output) => {
+            let term = exit_with(INPUT_ERROR, load_input(&input, args.target, backend.as_ref()).await);
+            if args.filmstrip {
+                render_filmstrip(&reduce_trace(term, &args.reduction), &args.export).save_png(&output).unwrap();
+            } else if args.trace {
+                export_trace_png(&reduce_trace(term, &args.reduction), &output, &args.export).unwrap();
+            } else {
+                export_png(&term, &output, &args.export).unwrap();
+            }
+        }
+        Command::Text(input) => {
+            let term = exit_with(INPUT_ERROR, load_input(&input, args.target, backend.as_ref()).await);
+            if args.trace {
+                let trace = reduce_trace(term, &args.reduction);
+                print!("{}", trace_to_text(&trace, args.export.style));
+                if let Some(target) = result_target(&input, args.target) {
+                    println!("result{}", describe_result(&trace.terms[trace.terms.len() - 1], target));
+                }
+            } else {
+                print!("{}", diagram_to_text(&build_diagram(&term, args.export.style)));
+            }
+        }
+        Command::Eta(input) => {
+            let term = exit_with(INPUT_ERROR, load_input(&input, args.target, backend.as_ref()).await);
+            match args.reduction.eta {
+                EtaRule::Long(binders) => println!("{}", eta_long(&term, binders)),
+                _ => println!("{}", eta_normal_form(&term))
+            }
+        }
+        Command::Animate(input, output) => {
+            let trace = reduce_trace(exit_with(INPUT_ERROR, load_input(&input, args.target, backend.as_ref()).await), &args.reduction);
+            export_animation(&trace, &output, &args.export, result_target(&input, args.target)).unwrap();
+        }
+        Command::Repl => {
+            if let Err(e) = backend.check().await {
+                eprintln!("{e}");
+            }
+            let mut session = Session::default();
+            for line in std::io::stdin().lines() {
+                let line = line.unwrap();
+                let input = line.trim();
+                if input.is_empty() {
+                    continue;
+                }
+                match read_session_input(input, args.target, &session, backend.as_ref()).await {
+                    Ok((term, answer)) => {
+                        let trace = reduce_trace(term, &args.reduction);
+                        let target = result_target(input, args.target).unwrap_or(Target::Lambda);
+                        session.record(input.to_string(), answer, trace.terms[trace.terms.len() - 1].clone(), target);
+                        println!("{}", session.exchanges().last().unwrap().result);
+                    }
+                    Err(e) => println!("error: {e}")
+                }
+            }
+        }
+        Command::ServeMock(fixture, port) => {
+            let mock = exit_with(USAGE_ERROR, MockBackend::from_fixture(&fixture).map_err(|e| format!("Could not load {}: {e}", fixture.display())));
+            serve(mock, port, &model).unwrap();
+        }
+        Command::Window => {
+            let options = WindowCreationOptions::new_fullscreen_borderless();
+            let window = speedy2d::Window::new_with_options("Lambda", options).unwrap();
+            let mut handler = LambdaGraphicsHandler::new(Font::new(FONT_BYTES).unwrap(), args.reduction, args.export.style, Arc::clone(&backend));
+            if let Err(e) = backend.check().await {
+                handler.report(e);
+            }
+            window.run_loop(handler);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::expr::LambdaExpr;
+use crate::reduction::{beta_reduce_step, contract_at, StepInfo, Trace};
+
+pub(crate) struct ManualTrace {
+    pub(crate) trace: Trace,
+    undone: Vec<(LambdaExpr, Vec<StepInfo>)>,
+    normal_form: bool
+}
+
+impl ManualTrace {
+    pub(crate) fn new(term: LambdaExpr) -> Self {
+        let normal_form = beta_reduce_step(&mut term.clone()).is_empty();
+        Self {
+            trace: Trace {
+                terms: vec![term],
+                steps: vec![],
+            },
+            undone: vec![],
+            normal_form
+        }
+    }
+
+    pub(crate) fn current(&self) -> &LambdaExpr {
+        &self.trace.terms[self.trace.terms.len() - 1]
+    }
+
+    pub(crate) fn steps(&self) -> usize {
+        self.trace.steps.len()
+    }
+
+    pub(crate) fn is_normal_form(&self) -> bool {
+        self.normal_form
+    }
+
+    fn refresh(&mut self) {
+        self.normal_form = beta_reduce_step(&mut self.current().clone()).is_empty();
+    }
+
+    pub(crate) fn reduce_at(&mut self, path: &[usize]) -> bool {
+        let mut term = self.current().clone();
+        match contract_at(&mut term, path) {
+            Some(step) => {
+                self.trace.terms.push(term);
+                self.trace.steps.push(vec![step]);
+                self.undone.clear();
+                self.refresh();
+                true
+            }
+            None => false
+        }
+    }
+
+    pub(crate) fn undo(&mut self) -> bool {
+        match self.trace.steps.pop() {
+            Some(step) => {
+                let term = self.trace.terms.pop().unwrap();
+                self.undone.push((term, step));
+                self.refresh();
+                true
+            }
+            None => false
+        }
+    }
+
+    pub(crate) fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some((term, step)) => {
+                self.trace.terms.push(term);
+                self.trace.steps.push(step);
+                self.refresh();
+                true
+            }
+            None => false
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use serde_json::{json, Value};
+use crate::backend::{MockBackend, CORRECTION_PREFIX};
+
+fn read_request(stream: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok((request_line, body))
+}
+
+fn respond(mock: &MockBackend, model: &str, request_line: &str, body: &[u8]) -> (u16, String) {
+    if request_line.starts_with("GET /api/tags") {
+        return (200, json!({ "models": [{ "name": model, "modified_at": "1970-01-01T00:00:00Z", "size": 0 }] }).to_string());
+    }
+    if !request_line.starts_with("POST /api/chat") {
+        return (404, format!("The stand-in server only answers GET /api/tags and POST /api/chat, not {}", request_line.trim()));
+    }
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(x) => x,
+        Err(e) => return (400, format!("Invalid JSON: {e}"))
+    };
+    let user_messages = request["messages"].as_array()
+        .map(|messages| messages.iter().filter(|message| message["role"] == "user").filter_map(|message| message["content"].as_str()).collect::<Vec<&str>>())
+        .unwrap_or_default();
+    let attempt = user_messages.iter().rev().take_while(|content| content.starts_with(CORRECTION_PREFIX)).count();
+    let prompt = user_messages.iter().rev().nth(attempt).copied().unwrap_or_default();
+    match mock.respond(prompt, attempt) {
+        Ok(content) => (200, json!({
+            "model": request["model"],
+            "created_at": "1970-01-01T00:00:00Z",
+            "message": { "role": "assistant", "content": content },
+            "done": true
+        }).to_string()),
+        Err(e) => (404, e)
+    }
+}
+
+fn handle(mock: &MockBackend, model: &str, mut stream: TcpStream) -> io::Result<()> {
+    let (request_line, body) = read_request(&mut stream)?;
+    let (status, body) = respond(mock, model, &request_line, &body);
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        _ => "Not Found"
+    };
+    write!(stream, "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len())?;
+    stream.flush()
+}
+
+pub(crate) fn serve(mock: MockBackend, port: u16, model: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("listening on http://{}", listener.local_addr()?);
+    io::stdout().flush()?;
+    for stream in listener.incoming() {
+        if let Err(e) = stream.and_then(|stream| handle(&mock, model, stream)) {
+            eprintln!("{e}");
+        }
+    }
+    Ok(())
+}
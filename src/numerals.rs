@@ -1,3 +1,4 @@
+use crate::eta::eta_long;
 use crate::expr::{ExprType, LambdaExpr};
 
 pub(crate) fn church_inner(x: i32) -> LambdaExpr {
@@ -39,10 +40,14 @@ pub(crate) fn church(x: i32) -> LambdaExpr {
     }
 }
 
-pub(crate) fn unchurch(x: &LambdaExpr) -> i32 {
+fn unchurch_inner(x: &LambdaExpr) -> i32 {
     match x.expr_type {
         ExprType::Var => 0,
-        ExprType::Abs => unchurch(&x.children[0].children[0]),
-        ExprType::App => 1 + unchurch(&x.children[1])
+        ExprType::Abs => unchurch(x),
+        ExprType::App => 1 + unchurch_inner(&x.children[1])
     }
+}
+
+pub(crate) fn unchurch(x: &LambdaExpr) -> i32 {
+    unchurch_inner(&eta_long(x, 2).children[0].children[0])
 }
\ No newline at end of file
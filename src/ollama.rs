@@ -1,38 +1,128 @@
+use futures::future::BoxFuture;
 use ollama_rs::Ollama;
 use ollama_rs::generation::chat::ChatMessage;
 use ollama_rs::generation::chat::request::ChatMessageRequest;
-use crate::decoding::arithmetic_to_lambda;
-use crate::expr::LambdaExpr;
-use crate::reduction::beta_reduce_step;
-
-const SYSTEM_PROMPT_0: &str = "You are an accurate AI model tasked with translating a user's query \
-into a mathematical expression. You will ONLY output the expression, with NO parenthesis. The \
-expression MUST represent the question asked by the user. DO NOT simplify OR evaluate it. Use + to
-represent addition, - for subtraction, * for multiplication, / for division, and ! for factorial.
+use ollama_rs::generation::parameters::{FormatType, JsonStructure};
+use ollama_rs::models::ModelOptions;
+use tokio::time::timeout;
+use crate::backend::{Correction, Target, TranslationBackend};
+use crate::config::Config;
+use crate::session::Session;
+use crate::prelude::PRELUDE;
+use crate::structured::ArithmeticNode;
+
+pub(crate) const SYSTEM_PROMPT_0: &str = "You are an accurate AI model tasked with translating a user's query \
+into a mathematical expression. You will ONLY output the expression. The expression MUST represent \
+the question asked by the user. DO NOT simplify OR evaluate it. Use + to represent addition, - for \
+subtraction, * for multiplication, / for division, and ! for factorial. ! binds tightest, then * and /, \
+then + and -, each from left to right; use parentheses whenever the question groups differently.
+EXAMPLE
+USER: What is three plus seven, all divided by twelve?
+YOU: (3 + 7) / 12
+";
+pub(crate) const SYSTEM_PROMPT_STRUCTURED: &str = "You are an accurate AI model tasked with translating a user's \
+query into a mathematical expression tree. Answer with a JSON object whose \"op\" is \"number\" (with a \
+non-negative integer \"value\") or one of +, -, *, / and ! (with its operands, in order, in \"args\"). ! takes \
+one operand, the others two. Nest objects to show exactly which operation applies first. The tree MUST \
+represent the question asked by the user. DO NOT simplify OR evaluate it.
 EXAMPLE
-USER: What is three plus seven divided by twelve?
-YOU: 3 + 7 / 12
+USER: What is three plus seven, all divided by twelve?
+YOU: {\"op\": \"/\", \"args\": [{\"op\": \"+\", \"args\": [{\"op\": \"number\", \"value\": 3}, {\"op\": \"number\", \"value\": 7}]}, {\"op\": \"number\", \"value\": 12}]}
+";
+const SYSTEM_PROMPT_LAMBDA: &str = "You are an accurate AI model tasked with translating a user's question \
+into a single closed lambda calculus term. You will ONLY output the term. Write abstractions as \\x.body, \
+application by juxtaposition and parentheses for grouping, with single-letter variables. Numbers stand for \
+Church numerals. DO NOT reduce the term. You may use these names, separated by spaces:
 ";
 
-pub(crate) fn instantiate_ollama() -> Ollama {
-    Ollama::default()
+pub(crate) const DEFAULT_MODEL: &str = "llama3:latest";
+
+pub(crate) struct OllamaBackend {
+    ollama: Ollama,
+    config: Config
+}
+
+impl OllamaBackend {
+    pub(crate) fn new(config: Config) -> Self {
+        Self {
+            ollama: Ollama::new(config.host.clone(), config.port),
+            config
+        }
+    }
+
+    fn messages(&self, prompt: &str, target: Target, session: &Session, corrections: &[Correction]) -> Vec<ChatMessage> {
+        let mut messages = instructions(&self.config, target);
+        for exchange in session.exchanges() {
+            messages.push(ChatMessage::user(exchange.prompt.clone()));
+            messages.push(ChatMessage::assistant(exchange.answer.clone()));
+            messages.push(ChatMessage::system(format!("That gave {}. Later questions may refer to it by that name, or as ans while it is the latest result.", exchange.result)));
+        }
+        messages.push(ChatMessage::user(prompt.to_string()));
+        for correction in corrections {
+            messages.push(ChatMessage::assistant(correction.answer.clone()));
+            messages.push(ChatMessage::user(correction.message()));
+        }
+        messages
+    }
 }
 
-pub(crate) async fn handle_prompt(prompt: String, ollama: &mut Ollama) -> Vec<LambdaExpr> {
-    let mut history = vec![];
-    let res = ollama
-        .send_chat_messages_with_history(
-            &mut history,
-            ChatMessageRequest::new(
-                "llama3:latest".to_string(),
-                vec![ChatMessage::system(SYSTEM_PROMPT_0.to_string()), ChatMessage::user(prompt)]
-            )
-        )
-        .await;
-    let output = res.unwrap().message.content;
-    let message = output.replace("!", " !");
-    let mut expr = arithmetic_to_lambda(&message);
-    let mut terms = vec![expr.clone()];
-    while beta_reduce_step(&mut expr) { terms.push(expr.clone()) }
-    terms
-}
\ No newline at end of file
+/// The messages before the conversation: the system prompt and, for arithmetic, the configured examples.
+pub(crate) fn instructions(config: &Config, target: Target) -> Vec<ChatMessage> {
+    match target {
+        Target::Arithmetic => {
+            let mut messages = vec![ChatMessage::system(config.system_prompt.clone())];
+            for example in &config.examples {
+                messages.push(ChatMessage::user(example.user.clone()));
+                messages.push(ChatMessage::assistant(example.assistant.clone()));
+            }
+            messages
+        }
+        Target::Lambda => vec![ChatMessage::system(lambda_prompt())]
+    }
+}
+
+fn lambda_prompt() -> String {
+    let names = PRELUDE.iter().map(|(name, source)| format!("{name} = {source}\n")).collect::<String>();
+    format!("{SYSTEM_PROMPT_LAMBDA}{names}EXAMPLE\nUSER: Apply the identity function to K\nYOU: I K\n")
+}
+
+/// Ollama reports `llama3` as `llama3:latest`.
+fn same_model(configured: &str, installed: &str) -> bool {
+    configured == installed || (!configured.contains(':') && installed == format!("{configured}:latest"))
+}
+
+impl TranslationBackend for OllamaBackend {
+    fn translate<'a>(&'a self, prompt: &'a str, target: Target, session: &'a Session, corrections: &'a [Correction]) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let mut request = ChatMessageRequest::new(self.config.model.clone(), self.messages(prompt, target, session, corrections));
+            if self.config.structured && target == Target::Arithmetic {
+                request = request.format(FormatType::StructuredJson(JsonStructure::new::<ArithmeticNode>()));
+            }
+            if let Some(temperature) = self.config.temperature {
+                request = request.options(ModelOptions::default().temperature(temperature));
+            }
+            let res = timeout(self.config.timeout, self.ollama.send_chat_messages(request)).await
+                .map_err(|_| format!("Ollama did not answer within {}s", self.config.timeout.as_secs()))?
+                .map_err(|e| format!("Ollama request failed: {e}"))?;
+            Ok(res.message.content)
+        })
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async move {
+            let url = self.config.url();
+            let models = timeout(self.config.timeout, self.ollama.list_local_models()).await
+                .map_err(|_| format!("Ollama at {url} did not answer within {}s", self.config.timeout.as_secs()))?
+                .map_err(|e| format!("Could not reach Ollama at {url}: {e}"))?;
+            if models.iter().any(|x| same_model(&self.config.model, &x.name)) {
+                return Ok(());
+            }
+            let installed = models.iter().map(|x| x.name.as_str()).collect::<Vec<&str>>();
+            if installed.is_empty() {
+                Err(format!("Model {} is not available: {url} has no models installed, try `ollama pull {}`", self.config.model, self.config.model))
+            } else {
+                Err(format!("Model {} is not available on {url}; it has {}", self.config.model, installed.join(", ")))
+            }
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use crate::backend::Target;
+use crate::decoding::interpret_expr;
+use crate::expr::{decode_id, ExprType, LambdaExpr};
+use crate::session::Session;
+
+/// Named terms that may appear, separated by spaces, inside a lambda term.
+pub(crate) const PRELUDE: [(&str, &str); 24] = [
+    ("I", "λx.x"),
+    ("K", "λx.λy.x"),
+    ("S", "λx.λy.λz.xz(yz)"),
+    ("Y", "λf.(λx.f(xx))(λx.f(xx))"),
+    ("true", "λa.λb.a"),
+    ("false", "λa.λb.b"),
+    ("and", "λp.λq.pqp"),
+    ("or", "λp.λq.ppq"),
+    ("not", "λp.λa.λb.pba"),
+    ("if", "λp.λa.λb.pab"),
+    ("pair", "λa.λb.λf.fab"),
+    ("fst", "λp.p(λa.λb.a)"),
+    ("snd", "λp.p(λa.λb.b)"),
+    ("nil", "λa.λb.b"),
+    ("cons", "λa.λb.λf.fab"),
+    ("head", "λp.p(λa.λb.a)"),
+    ("tail", "λp.p(λa.λb.b)"),
+    ("isnil", "λl.l(λh.λt.λd.λa.λb.b)(λa.λb.a)"),
+    ("succ", "λn.λf.λx.f(nfx)"),
+    ("pred", "λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u)"),
+    ("add", "λm.λn.λf.λx.mf(nfx)"),
+    ("sub", "λm.λn.n(λn.λf.λx.n(λg.λh.h(gf))(λu.x)(λu.u))m"),
+    ("mul", "λm.λn.λf.λx.m(nf)x"),
+    ("iszero", "λn.n(λx.(λa.λb.b))(λa.λb.a)"),
+];
+
+fn numeral_source(n: usize) -> String {
+    let body = (0..n).fold("x".to_string(), |body, _| if body.len() == 1 { format!("f{body}") } else { format!("f({body})") });
+    format!("λf.λx.{body}")
+}
+
+/// Replaces every whole word that names a prelude term, and every number, with its definition.
+pub(crate) fn expand_prelude(input: &str) -> Result<String, String> {
+    let mut output = String::new();
+    let mut word = String::new();
+    for c in input.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if let Some((_, source)) = PRELUDE.iter().find(|(name, _)| *name == word) {
+            output += &format!("({source})");
+        } else if !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()) {
+            let n = word.parse().map_err(|_| format!("{word} is too large"))?;
+            output += &format!("({})", numeral_source(n));
+        } else {
+            output += &word;
+        }
+        word.clear();
+        output.push(c);
+    }
+    output.pop();
+    Ok(output)
+}
+
+fn free_variable(expr: &LambdaExpr, bound: &mut Vec<usize>) -> Option<usize> {
+    match expr.expr_type {
+        ExprType::Var => (!bound.contains(&expr.id)).then_some(expr.id),
+        ExprType::Abs => {
+            bound.push(expr.id);
+            let free = free_variable(&expr.children[0], bound);
+            bound.pop();
+            free
+        }
+        ExprType::App => free_variable(&expr.children[0], bound).or_else(|| free_variable(&expr.children[1], bound))
+    }
+}
+
+pub(crate) fn closed(term: LambdaExpr) -> Result<LambdaExpr, String> {
+    match free_variable(&term, &mut vec![]) {
+        Some(id) => Err(format!("Free variable {}", decode_id(id))),
+        None => Ok(term)
+    }
+}
+
+/// Reads a closed lambda term written with the prelude and earlier results, e.g. `I K` or `\x.add x ans`.
+pub(crate) fn parse_prelude_term(input: &str, session: &Session) -> Result<LambdaExpr, String> {
+    let input = session.expand(input.trim().trim_matches('`'), Target::Lambda)?;
+    let source = expand_prelude(&input.replace('\\', "λ"))?.replace(' ', "");
+    let term = session.substitute(interpret_expr(&source).ok_or_else(|| "Not a lambda term: expected λx.body, applications and prelude names".to_string())?);
+    let variable = match free_variable(&term, &mut vec![]) {
+        Some(id) => decode_id(id),
+        None => return Ok(term)
+    };
+    let unknown = input.split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|word| word.len() > 1 && word.starts_with(variable.as_str()) && !word.chars().all(|c| c.is_ascii_digit()));
+    match unknown {
+        Some(word) => Err(format!("Unknown name {word}; the prelude has {}", PRELUDE.map(|(name, _)| name).join(" "))),
+        None => Err(format!("Free variable {variable}"))
+    }
+}
+
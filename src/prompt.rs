@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = ".lambda_history";
+const HISTORY_LIMIT: usize = 500;
+const CARET: char = '|';
+
+pub(crate) fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+pub(crate) struct PromptEditor {
+    text: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    history_index: Option<usize>,
+    draft: String,
+    history_path: Option<PathBuf>
+}
+
+impl PromptEditor {
+    pub(crate) fn new(history_path: Option<PathBuf>) -> Self {
+        let history = history_path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().filter(|x| !x.is_empty()).map(|x| x.to_string()).collect::<Vec<String>>())
+            .unwrap_or_default();
+        Self {
+            text: vec![],
+            cursor: 0,
+            history: history[history.len().saturating_sub(HISTORY_LIMIT)..].to_vec(),
+            history_index: None,
+            draft: String::new(),
+            history_path
+        }
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub(crate) fn with_caret(&self) -> String {
+        let mut text = self.text.clone();
+        text.insert(self.cursor, CARET);
+        text.into_iter().collect()
+    }
+
+    fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.replace("");
+    }
+
+    pub(crate) fn replace(&mut self, text: &str) {
+        self.set_text(text);
+        self.history_index = None;
+    }
+
+    pub(crate) fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        for c in text.chars().filter(|c| !c.is_control() || c.is_whitespace()) {
+            self.insert(if c.is_whitespace() { ' ' } else { c });
+        }
+    }
+
+    pub(crate) fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub(crate) fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn word_start(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && self.text[i - 1] == ' ' {
+            i -= 1;
+        }
+        while i > 0 && self.text[i - 1] != ' ' {
+            i -= 1;
+        }
+        i
+    }
+
+    fn word_end(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.text.len() && self.text[i] == ' ' {
+            i += 1;
+        }
+        while i < self.text.len() && self.text[i] != ' ' {
+            i += 1;
+        }
+        i
+    }
+
+    pub(crate) fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    pub(crate) fn left(&mut self, word: bool) {
+        self.cursor = if word { self.word_start() } else { self.cursor.saturating_sub(1) };
+    }
+
+    pub(crate) fn right(&mut self, word: bool) {
+        self.cursor = if word { self.word_end() } else { (self.cursor + 1).min(self.text.len()) };
+    }
+
+    pub(crate) fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub(crate) fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    pub(crate) fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(0) => return,
+            Some(i) => i - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.text();
+                self.history.len() - 1
+            }
+        };
+        self.history_index = Some(index);
+        self.set_text(&self.history[index].clone());
+    }
+
+    pub(crate) fn history_next(&mut self) {
+        match self.history_index {
+            Some(i) if i + 1 < self.history.len() => {
+                self.history_index = Some(i + 1);
+                self.set_text(&self.history[i + 1].clone());
+            }
+            Some(_) => {
+                self.history_index = None;
+                self.set_text(&self.draft.clone());
+            }
+            None => {}
+        }
+    }
+
+    /// Remembers the prompt's text, on one line so a pasted multi-line query stays one entry, and
+    /// rewrites the history file with the last [`HISTORY_LIMIT`] entries.
+    pub(crate) fn commit(&mut self) {
+        let text = self.text().lines().collect::<Vec<&str>>().join(" ");
+        self.history_index = None;
+        if text.trim().is_empty() || self.history.last() == Some(&text) {
+            return;
+        }
+        self.history.push(text.clone());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.history_path {
+            let _ = fs::write(path, self.history.iter().map(|x| format!("{x}\n")).collect::<String>());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_file_keeps_the_last_entries_one_per_line() {
+        let path = std::env::temp_dir().join(format!("lambda-history-{}", std::process::id()));
+        fs::write(&path, (0..HISTORY_LIMIT).map(|i| format!("{i}\n")).collect::<String>()).unwrap();
+        let mut prompt = PromptEditor::new(Some(path.clone()));
+        "what is two\r\nplus three".chars().for_each(|c| prompt.insert(c));
+        prompt.commit();
+        let history = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let lines = history.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), HISTORY_LIMIT);
+        assert_eq!((lines[0], lines[HISTORY_LIMIT - 1]), ("1", "what is two plus three"));
+    }
+}
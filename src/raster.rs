@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use speedy2d::color::Color;
+use crate::diagrams::{build_diagram, Diagram};
+use crate::export::ExportOptions;
+use crate::expr::LambdaExpr;
+use crate::graphics::{layout_scene, Scene, ScreenLabel, ScreenLine, View, FONT_BYTES, LINE_THICKNESS, TEXT_PADDING, TEXT_SIZE, TEXT_WIDTH};
+use crate::reduction::{beta_reduce_step, StepInfo, Trace};
+
+const FILMSTRIP_CELL: (u32, u32) = (320, 240);
+
+pub(crate) struct Canvas {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: u32, height: u32, background: Option<Color>) -> Self {
+        let fill = background.map(|x| [x.r(), x.g(), x.b(), x.a()]).unwrap_or([0.0; 4]);
+        Self {
+            width,
+            height,
+            pixels: vec![fill; (width * height) as usize],
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: Color, coverage: f32) {
+        let alpha = color.a() * coverage;
+        if alpha <= 0.0 {
+            return;
+        }
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let out_alpha = alpha + pixel[3] * (1.0 - alpha);
+        for (channel, source) in [color.r(), color.g(), color.b()].into_iter().enumerate() {
+            pixel[channel] = (source * alpha + pixel[channel] * pixel[3] * (1.0 - alpha)) / out_alpha;
+        }
+        pixel[3] = out_alpha;
+    }
+
+    pub(crate) fn draw_line(&mut self, line: &ScreenLine) {
+        let (dx, dy) = (line.end.0 - line.start.0, line.end.1 - line.start.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 || self.width == 0 || self.height == 0 {
+            return;
+        }
+        let (ux, uy) = (dx / length, dy / length);
+        let half = line.thickness / 2.0;
+        let min_x = (line.start.0.min(line.end.0) - half - 1.0).floor().max(0.0) as u32;
+        let min_y = (line.start.1.min(line.end.1) - half - 1.0).floor().max(0.0) as u32;
+        let max_x = (line.start.0.max(line.end.0) + half + 1.0).ceil().clamp(0.0, (self.width - 1) as f32) as u32;
+        let max_y = (line.start.1.max(line.end.1) + half + 1.0).ceil().clamp(0.0, (self.height - 1) as f32) as u32;
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let (px, py) = (x as f32 + 0.5 - line.start.0, y as f32 + 0.5 - line.start.1);
+                let along = px * ux + py * uy;
+                let across = (px * uy - py * ux).abs();
+                let coverage = (half - across + 0.5).clamp(0.0, 1.0)
+                    * (along + 0.5).clamp(0.0, 1.0)
+                    * (length - along + 0.5).clamp(0.0, 1.0);
+                self.blend(x, y, line.color, coverage);
+            }
+        }
+    }
+
+    pub(crate) fn draw_lines(&mut self, lines: &[ScreenLine], offset: (f32, f32)) {
+        for line in lines {
+            self.draw_line(&ScreenLine {
+                start: (line.start.0 + offset.0, line.start.1 + offset.1),
+                end: (line.end.0 + offset.0, line.end.1 + offset.1),
+                thickness: line.thickness,
+                color: line.color
+            });
+        }
+    }
+
+    pub(crate) fn draw_text(&mut self, font: &FontRef, text: &str, position: (f32, f32), width: f32, color: Color) {
+        self.draw_text_sized(font, text, position, width, TEXT_SIZE, color);
+    }
+
+    fn draw_text_sized(&mut self, font: &FontRef, text: &str, position: (f32, f32), width: f32, size: f32, color: Color) {
+        let scale = PxScale::from(size);
+        let scaled = font.as_scaled(scale);
+        let mut caret = point(position.0, position.1 + scaled.ascent());
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if c == '\n' || caret.x + scaled.h_advance(id) > position.0 + width {
+                caret = point(position.0, caret.y + scaled.height() + scaled.line_gap());
+                if c == '\n' {
+                    continue;
+                }
+            }
+            if let Some(outlined) = font.outline_glyph(id.with_scale_and_position(scale, caret)) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, coverage| {
+                    let (x, y) = (bounds.min.x as i64 + x as i64, bounds.min.y as i64 + y as i64);
+                    if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+                        self.blend(x as u32, y as u32, color, coverage);
+                    }
+                });
+            }
+            caret.x += scaled.h_advance(id);
+        }
+    }
+
+    fn draw_label(&mut self, font: &FontRef, label: &ScreenLabel, offset: (f32, f32)) {
+        let scaled = font.as_scaled(PxScale::from(label.size));
+        let width: f32 = label.text.chars().map(|c| scaled.h_advance(scaled.glyph_id(c))).sum();
+        let position = (label.position.0 + offset.0 - width / 2.0, label.position.1 + offset.1 - scaled.height() / 2.0);
+        self.draw_text_sized(font, &label.text, position, f32::INFINITY, label.size, label.color);
+    }
+
+    pub(crate) fn draw_scene(&mut self, font: &FontRef, scene: &Scene, offset: (f32, f32)) {
+        self.draw_lines(&scene.lines, offset);
+        for label in &scene.labels {
+            self.draw_label(font, label, offset);
+        }
+    }
+
+    pub(crate) fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels.iter()
+            .flat_map(|pixel| pixel.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect()
+    }
+
+    pub(crate) fn save_png(&self, path: &Path) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.to_rgba8()).map_err(io::Error::other)
+    }
+}
+
+fn natural_size(diagram: &Diagram) -> (u32, u32) {
+    let margin = 2.0 * LINE_THICKNESS;
+    ((diagram.rightmost().0 + 2.0 * margin).ceil() as u32, (diagram.bottommost().1 + 2.0 * margin).ceil() as u32)
+}
+
+pub(crate) fn diagram_scene(diagram: &Diagram, size: (u32, u32), redexes: &[StepInfo], normal_form: bool, options: &ExportOptions) -> Scene {
+    let mut scene = layout_scene(diagram, (size.0 as f32, size.1 as f32), &View::default(), redexes, normal_form);
+    for line in &mut scene.lines {
+        line.thickness *= options.thickness / LINE_THICKNESS;
+    }
+    if let Some(color) = options.color {
+        for line in &mut scene.lines {
+            line.color = color;
+        }
+        for label in &mut scene.labels {
+            label.color = color;
+        }
+    }
+    scene
+}
+
+pub(crate) fn render_diagram(diagram: &Diagram, redexes: &[StepInfo], normal_form: bool, font: &FontRef, options: &ExportOptions) -> Canvas {
+    let size = options.size.unwrap_or_else(|| natural_size(diagram));
+    let mut canvas = Canvas::new(size.0, size.1, options.background);
+    canvas.draw_scene(font, &diagram_scene(diagram, size, redexes, normal_form, options), (0.0, 0.0));
+    canvas
+}
+
+pub(crate) fn export_png(term: &LambdaExpr, path: &Path, options: &ExportOptions) -> io::Result<()> {
+    let font = FontRef::try_from_slice(FONT_BYTES).unwrap();
+    let diagram = build_diagram(term, options.style);
+    let redexes = beta_reduce_step(&mut term.clone());
+    render_diagram(&diagram, &redexes, redexes.is_empty(), &font, options).save_png(path)
+}
+
+pub(crate) fn export_trace_png(trace: &Trace, dir: &Path, options: &ExportOptions) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let font = FontRef::try_from_slice(FONT_BYTES).unwrap();
+    for (i, term) in trace.terms.iter().enumerate() {
+        let diagram = build_diagram(term, options.style);
+        let redexes = trace.steps.get(i).map(|x| x.as_slice()).unwrap_or(&[]);
+        render_diagram(&diagram, redexes, i + 1 == trace.terms.len(), &font, options).save_png(&dir.join(format!("{:04}.png", i)))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn render_filmstrip(trace: &Trace, options: &ExportOptions) -> Canvas {
+    let cell = options.size.unwrap_or(FILMSTRIP_CELL);
+    let columns = (trace.terms.len() as f32).sqrt().ceil() as u32;
+    let rows = (trace.terms.len() as u32).div_ceil(columns);
+    let mut canvas = Canvas::new(cell.0 * columns, cell.1 * rows, options.background);
+    let font = FontRef::try_from_slice(FONT_BYTES).unwrap();
+    for (i, term) in trace.terms.iter().enumerate() {
+        let diagram = build_diagram(term, options.style);
+        let redexes = trace.steps.get(i).map(|x| x.as_slice()).unwrap_or(&[]);
+        let scene = diagram_scene(&diagram, cell, redexes, i + 1 == trace.terms.len(), options);
+        let offset = ((i as u32 % columns * cell.0) as f32, (i as u32 / columns * cell.1) as f32);
+        canvas.draw_scene(&font, &scene, offset);
+    }
+    canvas
+}
+
+/// The window's text panel as `(padding, width)`, shrunk to half of frames narrower than twice its size.
+fn text_panel(width: u32) -> (f32, f32) {
+    let scale = (width as f32 / 2.0 / (TEXT_WIDTH + 2.0 * TEXT_PADDING)).min(1.0);
+    (TEXT_PADDING * scale, TEXT_WIDTH * scale)
+}
+
+pub(crate) fn render_frame(term: &LambdaExpr, redexes: &[StepInfo], normal_form: bool, text: &str, font: &FontRef, size: (u32, u32), options: &ExportOptions) -> Canvas {
+    let mut canvas = Canvas::new(size.0, size.1, options.background);
+    let (padding, width) = text_panel(size.0);
+    let area = (size.0 as f32 - width - 2.0 * padding, size.1 as f32);
+    let diagram = build_diagram(term, options.style);
+    canvas.draw_scene(font, &diagram_scene(&diagram, (area.0 as u32, area.1 as u32), redexes, normal_form, options), (0.0, 0.0));
+    canvas.draw_text(font, text, (area.0 + padding, padding), width, options.color.unwrap_or(Color::WHITE));
+    canvas
+}
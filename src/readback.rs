@@ -0,0 +1,85 @@
+use crate::backend::Target;
+use crate::eta::{eta_long, is_free};
+use crate::expr::{ExprType, LambdaExpr};
+use crate::numerals::unchurch;
+
+/// Splits `λa.λb.body` into its two binders and body.
+fn two_binders(expr: &LambdaExpr) -> Option<(usize, usize, &LambdaExpr)> {
+    let inner = expr.children.first().filter(|_| expr.expr_type == ExprType::Abs)?;
+    let body = inner.children.first().filter(|_| inner.expr_type == ExprType::Abs)?;
+    (expr.id != inner.id).then_some((expr.id, inner.id, body))
+}
+
+pub(crate) fn numeral(expr: &LambdaExpr) -> Option<i32> {
+    let expr = eta_long(expr, 2);
+    let (f, x, mut body) = two_binders(&expr)?;
+    let mut n = 0;
+    while body.expr_type == ExprType::App {
+        if body.children[0].expr_type != ExprType::Var || body.children[0].id != f {
+            return None;
+        }
+        body = &body.children[1];
+        n += 1;
+    }
+    (body.expr_type == ExprType::Var && body.id == x).then_some(n)
+}
+
+fn boolean(expr: &LambdaExpr) -> Option<bool> {
+    let (a, b, body) = two_binders(expr)?;
+    match body.expr_type {
+        ExprType::Var if body.id == a => Some(true),
+        ExprType::Var if body.id == b => Some(false),
+        _ => None
+    }
+}
+
+/// `λf.f head tail`, as built by `pair` and `cons`.
+fn pair(expr: &LambdaExpr) -> Option<(&LambdaExpr, &LambdaExpr)> {
+    let body = expr.children.first().filter(|_| expr.expr_type == ExprType::Abs)?;
+    let (function, tail) = (body.children.first()?, body.children.get(1)?);
+    let (selector, head) = (function.children.first()?, function.children.get(1)?);
+    let shape = body.expr_type == ExprType::App && function.expr_type == ExprType::App && selector.expr_type == ExprType::Var && selector.id == expr.id;
+    (shape && !is_free(head, expr.id) && !is_free(tail, expr.id)).then_some((head, tail))
+}
+
+fn list(expr: &LambdaExpr) -> Option<Vec<String>> {
+    if boolean(expr) == Some(false) {
+        return Some(vec![]);
+    }
+    let (head, tail) = pair(expr)?;
+    let mut items = vec![read_element(head)];
+    items.extend(list(tail)?);
+    Some(items)
+}
+
+fn read_element(expr: &LambdaExpr) -> String {
+    read_back(expr)
+        .map(|x| if boolean(expr) == Some(false) { "0".to_string() } else { x })
+        .unwrap_or_else(|| expr.to_string())
+}
+
+/// Reads a normal form as a numeral, boolean, list or pair, whichever its shape matches.
+/// `0`, `false` and `[]` share one term, so that reading names all three.
+pub(crate) fn read_back(expr: &LambdaExpr) -> Option<String> {
+    if boolean(expr) == Some(false) {
+        return Some("0 = false = []".to_string());
+    }
+    if let Some(n) = numeral(expr) {
+        return Some(n.to_string());
+    }
+    if boolean(expr) == Some(true) {
+        return Some("true".to_string());
+    }
+    if let Some(items) = list(expr) {
+        return Some(format!("[{}]", items.join(", ")));
+    }
+    pair(expr).map(|(a, b)| format!("({}, {})", read_element(a), read_element(b)))
+}
+
+/// The ` = …` suffix shown after a normal form reached from `target`.
+pub(crate) fn describe_result(expr: &LambdaExpr, target: Target) -> String {
+    match target {
+        Target::Arithmetic => format!(" = {}", unchurch(expr)),
+        Target::Lambda => read_back(expr).map(|x| format!(" = {x}")).unwrap_or_default()
+    }
+}
@@ -1,4 +1,8 @@
-use crate::expr::{ExprType, LambdaExpr};
+use std::cmp::Reverse;
+use crate::eta::{eta_long, eta_reduce_step};
+use crate::expr::{decode_id, ExprType, LambdaExpr};
+
+const PARALLEL_THRESHOLD: usize = 2000;
 
 fn substitute(expr: &mut LambdaExpr, from: usize, to: LambdaExpr) {
     if expr.id == from {
@@ -21,20 +25,316 @@ fn substitute(expr: &mut LambdaExpr, from: usize, to: LambdaExpr) {
     }
 }
 
-pub(crate) fn beta_reduce_step(expr: &mut LambdaExpr) -> bool {
-    if expr.expr_type == ExprType::App && expr.children[0].expr_type == ExprType::Abs {
-        expr.expr_type = expr.children[0].children[0].expr_type.clone();
-        expr.id = expr.children[0].children[0].id;
-        let from_tmp = expr.children[0].id;
-        let to_tmp = expr.children[1].clone();
-        expr.children = expr.children[0].children[0].children.clone();
-        substitute(expr, from_tmp, to_tmp);
-        true
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Rule {
+    Beta,
+    Eta,
+    EtaExpand,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct StepInfo {
+    pub(crate) rule: Rule,
+    pub(crate) path: Vec<usize>,
+    pub(crate) var: usize,
+    pub(crate) arg: Option<LambdaExpr>,
+    pub(crate) size_delta: isize,
+}
+
+impl StepInfo {
+    /// One line for the text trace, e.g. `β at [0]: x := λy.y, size -2`.
+    pub(crate) fn describe(&self) -> String {
+        let size = format!("size {}{}", if self.size_delta > 0 { "+" } else { "" }, self.size_delta);
+        match (self.rule, &self.arg) {
+            (Rule::Beta, Some(arg)) => format!("β at {:?}: {} := {arg}, {size}", self.path, decode_id(self.var)),
+            (Rule::Beta, None) => format!("β at {:?}, {size}", self.path),
+            (Rule::Eta, _) => format!("η at {:?}: λ{} dropped, {size}", self.path, decode_id(self.var)),
+            (Rule::EtaExpand, _) => format!("η-expanded, {size}")
+        }
+    }
+}
+
+pub(crate) struct Trace {
+    pub(crate) terms: Vec<LambdaExpr>,
+    pub(crate) steps: Vec<Vec<StepInfo>>,
+}
+
+fn is_redex(expr: &LambdaExpr) -> bool {
+    expr.expr_type == ExprType::App && expr.children[0].expr_type == ExprType::Abs
+}
+
+fn contract(expr: &mut LambdaExpr, path: Vec<usize>) -> StepInfo {
+    let size_before = expr.size() as isize;
+    expr.expr_type = expr.children[0].children[0].expr_type.clone();
+    expr.id = expr.children[0].children[0].id;
+    let from_tmp = expr.children[0].id;
+    let to_tmp = expr.children[1].clone();
+    expr.children = expr.children[0].children[0].children.clone();
+    substitute(expr, from_tmp, to_tmp.clone());
+    StepInfo {
+        rule: Rule::Beta,
+        path,
+        var: from_tmp,
+        arg: Some(to_tmp),
+        size_delta: expr.size() as isize - size_before,
+    }
+}
+
+fn beta_reduce_at(expr: &mut LambdaExpr, path: &mut Vec<usize>, steps: &mut Vec<StepInfo>) {
+    if is_redex(expr) {
+        steps.push(contract(expr, path.clone()));
     } else {
-        let mut found_redex = false;
-        for child in &mut expr.children {
-            found_redex |= beta_reduce_step(child);
+        for (i, child) in expr.children.iter_mut().enumerate() {
+            path.push(i);
+            beta_reduce_at(child, path, steps);
+            path.pop();
+        }
+    }
+}
+
+pub(crate) fn beta_reduce_step(expr: &mut LambdaExpr) -> Vec<StepInfo> {
+    let mut steps = vec![];
+    beta_reduce_at(expr, &mut vec![], &mut steps);
+    steps
+}
+
+/// Redexes with the paths that lead to them.
+type Redexes<'a> = Vec<(Vec<usize>, &'a mut LambdaExpr)>;
+
+fn outermost_redexes<'a>(expr: &'a mut LambdaExpr, path: &mut Vec<usize>, redexes: &mut Redexes<'a>) {
+    if is_redex(expr) {
+        redexes.push((path.clone(), expr));
+    } else {
+        for (i, child) in expr.children.iter_mut().enumerate() {
+            path.push(i);
+            outermost_redexes(child, path, redexes);
+            path.pop();
         }
-        found_redex
     }
-}
\ No newline at end of file
+}
+
+/// Contracts the outermost redexes like [`beta_reduce_step`], splitting them between `workers`
+/// threads once they add up to [`PARALLEL_THRESHOLD`] nodes.
+pub(crate) fn beta_reduce_step_parallel(expr: &mut LambdaExpr, workers: usize) -> Vec<StepInfo> {
+    parallel_step(expr, workers, PARALLEL_THRESHOLD)
+}
+
+fn parallel_step(expr: &mut LambdaExpr, workers: usize, threshold: usize) -> Vec<StepInfo> {
+    let mut redexes = vec![];
+    outermost_redexes(expr, &mut vec![], &mut redexes);
+    let mut sized: Vec<(usize, Vec<usize>, &mut LambdaExpr)> = redexes.into_iter().map(|(path, redex)| (redex.size(), path, redex)).collect();
+    let total_size: usize = sized.iter().map(|(size, _, _)| size).sum();
+    if sized.len() < 2 || workers < 2 || total_size < threshold {
+        return sized.into_iter().map(|(_, path, redex)| contract(redex, path)).collect();
+    }
+    sized.sort_by_key(|(size, _, _)| Reverse(*size));
+    let mut queues: Vec<(usize, Redexes)> = (0..workers.min(sized.len())).map(|_| (0, vec![])).collect();
+    for (size, path, redex) in sized {
+        let queue = queues.iter_mut().min_by_key(|(load, _)| *load).unwrap();
+        queue.0 += size;
+        queue.1.push((path, redex));
+    }
+    let mut steps: Vec<StepInfo> = std::thread::scope(|scope| {
+        let handles: Vec<_> = queues.into_iter().map(|(_, queue)| {
+            scope.spawn(move || {
+                queue.into_iter().map(|(path, redex)| contract(redex, path)).collect::<Vec<StepInfo>>()
+            })
+        }).collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    });
+    steps.sort_by(|a, b| a.path.cmp(&b.path));
+    steps
+}
+
+fn subterm<'a>(expr: &'a LambdaExpr, path: &[usize]) -> &'a LambdaExpr {
+    path.iter().fold(expr, |node, i| &node.children[*i])
+}
+
+pub(crate) fn redex_around(expr: &LambdaExpr, path: &[usize]) -> Option<Vec<usize>> {
+    (0..=path.len()).rev()
+        .map(|len| &path[..len])
+        .find(|prefix| is_redex(subterm(expr, prefix)))
+        .map(|prefix| prefix.to_vec())
+}
+
+pub(crate) fn contract_at(expr: &mut LambdaExpr, path: &[usize]) -> Option<StepInfo> {
+    let node = path.iter().fold(expr, |node, i| &mut node.children[*i]);
+    if !is_redex(node) {
+        return None;
+    }
+    Some(contract(node, path.to_vec()))
+}
+
+pub(crate) fn origin_path(before: &LambdaExpr, steps: &[StepInfo], path: &[usize]) -> Option<Vec<usize>> {
+    for step in steps {
+        if !path.starts_with(&step.path) {
+            continue;
+        }
+        let mut origin = step.path.clone();
+        origin.extend([0, 0]);
+        return match step.rule {
+            Rule::Beta => {
+                let rest = &path[step.path.len()..];
+                let mut node = subterm(before, &origin);
+                let mut shadowed = false;
+                for (depth, i) in rest.iter().enumerate() {
+                    if node.expr_type == ExprType::Var && node.id == step.var && !shadowed {
+                        return Some([&step.path[..], &[1], &rest[depth..]].concat());
+                    }
+                    shadowed |= node.expr_type == ExprType::Abs && node.id == step.var;
+                    node = node.children.get(*i)?;
+                    origin.push(*i);
+                }
+                if node.expr_type == ExprType::Var && node.id == step.var && !shadowed {
+                    return Some([&step.path[..], &[1]].concat());
+                }
+                Some(origin)
+            }
+            Rule::Eta => {
+                origin.extend(&path[step.path.len()..]);
+                Some(origin)
+            }
+            Rule::EtaExpand => None
+        };
+    }
+    Some(path.to_vec())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum EtaRule {
+    Off,
+    Reduce,
+    Long(usize),
+}
+
+#[derive(Clone)]
+pub(crate) struct ReductionOptions {
+    pub(crate) workers: usize,
+    pub(crate) eta: EtaRule,
+}
+
+impl ReductionOptions {
+    pub(crate) fn describe(&self) -> String {
+        let mut description = String::from("outermost");
+        if self.workers > 1 {
+            description += &format!(", {} workers", self.workers);
+        }
+        match self.eta {
+            EtaRule::Off => {}
+            EtaRule::Reduce => description += ", η-reduce",
+            EtaRule::Long(_) => description += ", η-long"
+        }
+        description
+    }
+}
+
+impl Default for ReductionOptions {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            eta: EtaRule::Off,
+        }
+    }
+}
+
+pub(crate) fn reduce_step(expr: &mut LambdaExpr, options: &ReductionOptions) -> Vec<StepInfo> {
+    let steps = if options.workers > 1 {
+        beta_reduce_step_parallel(expr, options.workers)
+    } else {
+        beta_reduce_step(expr)
+    };
+    if !steps.is_empty() {
+        return steps;
+    }
+    match options.eta {
+        EtaRule::Off => vec![],
+        EtaRule::Reduce => eta_reduce_step(expr),
+        EtaRule::Long(binders) => {
+            let long = eta_long(expr, binders);
+            if long == *expr {
+                return vec![];
+            }
+            let size_delta = long.size() as isize - expr.size() as isize;
+            *expr = long;
+            vec![StepInfo {
+                rule: Rule::EtaExpand,
+                path: vec![],
+                var: 0,
+                arg: None,
+                size_delta,
+            }]
+        }
+    }
+}
+
+/// Reduces `expr` as [`reduce_trace`] would without keeping the terms in between.
+pub(crate) fn normal_form(mut expr: LambdaExpr, options: &ReductionOptions) -> LambdaExpr {
+    while !reduce_step(&mut expr, options).is_empty() {}
+    expr
+}
+
+pub(crate) fn reduce_trace(mut expr: LambdaExpr, options: &ReductionOptions) -> Trace {
+    let mut trace = Trace {
+        terms: vec![expr.clone()],
+        steps: vec![],
+    };
+    loop {
+        let steps = reduce_step(&mut expr, options);
+        if steps.is_empty() {
+            break;
+        }
+        trace.terms.push(expr.clone());
+        trace.steps.push(steps);
+    }
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoding::interpret_expr;
+
+    fn term(source: &str) -> LambdaExpr {
+        interpret_expr(&source.to_string()).unwrap()
+    }
+
+    #[test]
+    fn beta_step_records_the_redex() {
+        let mut expr = term("λz.(λx.xx)(λy.y)");
+        let steps = beta_reduce_step(&mut expr);
+        assert_eq!(steps.len(), 1);
+        let step = &steps[0];
+        assert_eq!(step.rule, Rule::Beta);
+        assert_eq!(step.path, vec![0]);
+        assert_eq!(step.var, 'x' as usize);
+        assert_eq!(step.arg, Some(term("λy.y")));
+        assert_eq!(step.size_delta, -2);
+        assert_eq!(expr, term("λz.(λy.y)(λy.y)"));
+    }
+
+    #[test]
+    fn parallel_steps_record_each_redex() {
+        let mut expr = term("(λx.x)(λy.y)((λz.zz)(λw.w))");
+        let steps = beta_reduce_step_parallel(&mut expr, 2);
+        let mut found = steps.iter().map(|x| (x.path.clone(), x.var, x.size_delta)).collect::<Vec<(Vec<usize>, usize, isize)>>();
+        found.sort();
+        assert_eq!(found, vec![(vec![0], 'x' as usize, -3), (vec![1], 'z' as usize, -2)]);
+        let inner = steps.iter().find(|x| x.path == vec![1]).unwrap();
+        assert_eq!(inner.describe(), "β at [1]: z := λw.w, size -2");
+    }
+
+    #[test]
+    fn threads_match_the_sequential_steps() {
+        let mut sequential = term("λs.s((λx.x(xx))(λy.y))((λz.zz)(λw.w))((λa.a)(λb.λc.bc))((λd.d)(λe.e))");
+        let mut parallel = sequential.clone();
+        loop {
+            let expected = beta_reduce_step(&mut sequential);
+            let steps = parallel_step(&mut parallel, 3, 0);
+            assert_eq!(format!("{steps:?}"), format!("{expected:?}"));
+            assert_eq!(parallel, sequential);
+            if steps.is_empty() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::backend::Target;
+use crate::decoding::{is_lambda_term, parse_open_expression};
+use crate::prelude::{closed, parse_prelude_term};
+use crate::expr::{ExprType, LambdaExpr};
+use crate::readback::{describe_result, numeral};
+
+const LATEST: &str = "ans";
+/// Results are spliced into lambda terms through free variables from the private use area.
+const PLACEHOLDER: u32 = 0xE000;
+
+/// A finished query, replayed to the model so follow-ups can refer back to it.
+#[derive(Clone)]
+pub(crate) struct Exchange {
+    pub(crate) prompt: String,
+    pub(crate) answer: String,
+    pub(crate) result: String
+}
+
+/// Results of earlier queries, available as `ans` and `$1`, `$2`, ... until the session is cleared.
+#[derive(Clone, Default)]
+pub(crate) struct Session {
+    exchanges: Vec<Exchange>,
+    results: Vec<LambdaExpr>
+}
+
+impl Session {
+    pub(crate) fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.exchanges.clear();
+        self.results.clear();
+    }
+
+    pub(crate) fn record(&mut self, prompt: String, answer: String, term: LambdaExpr, target: Target) {
+        let reading = match describe_result(&term, target) {
+            x if x.is_empty() => term.to_string(),
+            x => x.trim_start_matches(" = ").to_string()
+        };
+        self.results.push(term);
+        self.exchanges.push(Exchange {
+            prompt,
+            answer,
+            result: format!("${} = {reading}", self.results.len())
+        });
+    }
+
+    /// A one-line reminder of what `ans` holds and how many results there are.
+    pub(crate) fn summary(&self) -> Option<String> {
+        let latest = self.exchanges.last()?;
+        Some(format!("ans is {}; earlier results are $1 to ${}", latest.result, self.results.len()))
+    }
+
+    fn lookup(&self, reference: &str) -> Result<usize, String> {
+        let index = if reference == LATEST {
+            self.results.len().checked_sub(1)
+        } else {
+            reference[1..].parse::<usize>().ok().filter(|x| (1..=self.results.len()).contains(x)).map(|x| x - 1)
+        };
+        index.ok_or_else(|| match self.results.len() {
+            0 => format!("{reference} refers to an earlier result, but there is none yet"),
+            n => format!("{reference} refers to an earlier result, but there are only $1 to ${n}")
+        })
+    }
+
+    fn reference_value(&self, reference: &str, target: Target) -> Result<String, String> {
+        let index = self.lookup(reference)?;
+        match target {
+            Target::Lambda => Ok(char::from_u32(PLACEHOLDER + index as u32).unwrap().to_string()),
+            Target::Arithmetic => match numeral(&self.results[index]) {
+                Some(n) => Ok(n.to_string()),
+                None => Err(format!("{reference} is {}, not a number", self.exchanges[index].result))
+            }
+        }
+    }
+
+    /// Rewrites `ans` and `$n` in `input`: into the numbers they hold for arithmetic, and into
+    /// placeholder variables for [`Session::substitute`] in lambda terms.
+    pub(crate) fn expand(&self, input: &str, target: Target) -> Result<String, String> {
+        let mut output = String::new();
+        let mut word = String::new();
+        for c in input.chars().chain(std::iter::once(' ')) {
+            if c.is_ascii_alphanumeric() || (c == '$' && word.is_empty()) {
+                word.push(c);
+                continue;
+            }
+            if word == LATEST || (word.len() > 1 && word.starts_with('$')) {
+                output += &self.reference_value(&word, target)?;
+            } else {
+                output += &word;
+            }
+            word.clear();
+            output.push(c);
+        }
+        output.pop();
+        Ok(output)
+    }
+
+    /// Replaces the placeholders left by [`Session::expand`] with the results they stand for.
+    pub(crate) fn substitute(&self, term: LambdaExpr) -> LambdaExpr {
+        let index = (term.id as u32).wrapping_sub(PLACEHOLDER) as usize;
+        match term.expr_type {
+            ExprType::Var if index < self.results.len() => self.results[index].clone(),
+            ExprType::Var => term,
+            _ => LambdaExpr {
+                children: term.children.into_iter().map(|child| self.substitute(child)).collect(),
+                ..term
+            }
+        }
+    }
+
+    /// Parses a typed term or arithmetic that may refer to earlier results. With `prelude`, lambda
+    /// terms may also use the prelude's names, which otherwise read as single-letter variables.
+    pub(crate) fn parse_expression(&self, input: &str, prelude: bool) -> Result<LambdaExpr, String> {
+        if prelude && is_lambda_term(input) {
+            return parse_prelude_term(input, self);
+        }
+        let target = if is_lambda_term(input) { Target::Lambda } else { Target::Arithmetic };
+        parse_open_expression(&self.expand(input, target)?).map(|term| self.substitute(term)).and_then(closed)
+    }
+}
@@ -0,0 +1,155 @@
+use schemars::JsonSchema;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::decoding::apply_combinator;
+use crate::expr::LambdaExpr;
+use crate::numerals::church;
+
+const NUMBER: &str = "number";
+const OPERATORS: [&str; 5] = ["+", "-", "*", "/", "!"];
+/// Ollama's schemas can't refer to themselves, so nesting is spelled out this many levels deep.
+const MAX_DEPTH: usize = 8;
+
+/// One node of the expression tree the model is asked to produce, e.g.
+/// `{"op": "+", "args": [{"op": "number", "value": 3}, {"op": "number", "value": 7}]}`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ArithmeticNode {
+    op: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<ArithmeticNode>
+}
+
+fn node_schema(depth: usize) -> Value {
+    if depth == 0 {
+        return json!({
+            "type": "object",
+            "properties": {
+                "op": { "type": "string", "enum": [NUMBER] },
+                "value": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["op", "value"]
+        });
+    }
+    let mut ops = vec![NUMBER];
+    ops.extend(OPERATORS);
+    json!({
+        "type": "object",
+        "properties": {
+            "op": { "type": "string", "enum": ops },
+            "value": { "type": "integer", "minimum": 0 },
+            "args": { "type": "array", "items": node_schema(depth - 1) }
+        },
+        "required": ["op"]
+    })
+}
+
+impl JsonSchema for ArithmeticNode {
+    fn schema_name() -> String {
+        "ArithmeticNode".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        serde_json::from_value(node_schema(MAX_DEPTH)).unwrap()
+    }
+}
+
+impl ArithmeticNode {
+    pub(crate) fn number(value: i32) -> Self {
+        Self { op: NUMBER.to_string(), value: Some(value), args: vec![] }
+    }
+
+    pub(crate) fn operation(op: char, args: Vec<ArithmeticNode>) -> Self {
+        Self { op: op.to_string(), value: None, args }
+    }
+
+    fn to_arithmetic(&self) -> String {
+        let operand = |node: &ArithmeticNode| match node.op.as_str() {
+            NUMBER => node.to_arithmetic(),
+            _ => format!("({})", node.to_arithmetic())
+        };
+        match self.op.as_str() {
+            NUMBER => self.value.unwrap_or_default().to_string(),
+            "!" => format!("{}!", operand(&self.args[0])),
+            op => self.args.iter().map(operand).collect::<Vec<String>>().join(&format!(" {op} "))
+        }
+    }
+
+    pub(crate) fn to_lambda(&self) -> Result<LambdaExpr, String> {
+        if self.op == NUMBER {
+            return match self.value {
+                Some(x) if x >= 0 && self.args.is_empty() => Ok(church(x)),
+                Some(x) if x < 0 => Err(format!("{x} is negative")),
+                Some(_) => Err("a number has no args".to_string()),
+                None => Err("a number needs a value".to_string())
+            };
+        }
+        let op = match OPERATORS.iter().find(|x| **x == self.op) {
+            Some(x) => x.chars().next().unwrap(),
+            None => return Err(format!("unknown op \"{}\", expected {NUMBER} or one of {}", self.op, OPERATORS.join(" ")))
+        };
+        let args = self.args.iter().map(ArithmeticNode::to_lambda).collect::<Result<Vec<LambdaExpr>, String>>()?;
+        match (op, args.len()) {
+            ('!', 1) => Ok(apply_combinator(op, args)),
+            ('!', n) => Err(format!("! takes one arg, got {n}")),
+            (_, 0 | 1) => Err(format!("{op} takes at least two args, got {}", args.len())),
+            _ => {
+                let mut args = args.into_iter();
+                let first = args.next().unwrap();
+                Ok(args.fold(first, |expr, arg| apply_combinator(op, vec![expr, arg])))
+            }
+        }
+    }
+}
+
+fn read_tree(input: &str) -> Result<(ArithmeticNode, LambdaExpr), String> {
+    let node: ArithmeticNode = serde_json::from_str(input).map_err(|e| format!("Invalid expression tree: {e}"))?;
+    let term = node.to_lambda().map_err(|e| format!("Invalid expression tree: {e}"))?;
+    Ok((node, term))
+}
+
+/// Reads a JSON expression tree as produced under [`ArithmeticNode`]'s schema.
+pub(crate) fn parse_structured(input: &str) -> Result<LambdaExpr, String> {
+    read_tree(input).map(|(_, term)| term)
+}
+
+/// Writes a JSON expression tree as arithmetic that parses back to the same term, e.g. `(3 + 7) / 12`.
+pub(crate) fn structured_to_arithmetic(input: &str) -> Result<String, String> {
+    read_tree(input).map(|(node, _)| node.to_arithmetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoding::parse_arithmetic;
+
+    fn round_trip(tree: &str) -> String {
+        let arithmetic = structured_to_arithmetic(tree).unwrap();
+        assert_eq!(parse_arithmetic(&arithmetic).unwrap(), parse_structured(tree).unwrap(), "{arithmetic}");
+        arithmetic
+    }
+
+    #[test]
+    fn nested_trees_keep_their_grouping() {
+        let tree = r#"{"op": "*", "args": [{"op": "+", "args": [{"op": "number", "value": 3}, {"op": "number", "value": 1}]}, {"op": "-", "args": [{"op": "number", "value": 4}, {"op": "number", "value": 2}]}]}"#;
+        assert_eq!(round_trip(tree), "(3 + 1) * (4 - 2)");
+    }
+
+    #[test]
+    fn n_ary_operators_fold_from_the_left() {
+        let minus = r#"{"op": "-", "args": [{"op": "number", "value": 9}, {"op": "number", "value": 3}, {"op": "number", "value": 2}]}"#;
+        assert_eq!(round_trip(minus), "9 - 3 - 2");
+        let divide = r#"{"op": "/", "args": [{"op": "number", "value": 8}, {"op": "/", "args": [{"op": "number", "value": 4}, {"op": "number", "value": 2}]}, {"op": "number", "value": 1}]}"#;
+        assert_eq!(round_trip(divide), "8 / (4 / 2) / 1");
+    }
+
+    #[test]
+    fn factorials_wrap_their_operand() {
+        let tree = r#"{"op": "!", "args": [{"op": "+", "args": [{"op": "number", "value": 1}, {"op": "!", "args": [{"op": "number", "value": 2}]}]}]}"#;
+        assert_eq!(round_trip(tree), "(1 + (2!))!");
+    }
+}
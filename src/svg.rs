@@ -0,0 +1,73 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use speedy2d::color::Color;
+use crate::diagrams::{build_diagram, Diagram, Direction, LABEL_SIZE};
+use crate::export::ExportOptions;
+use crate::expr::LambdaExpr;
+use crate::graphics::{line_color, source_color};
+use crate::reduction::{beta_reduce_step, StepInfo, Trace};
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", (color.r() * 255.0).round() as u8, (color.g() * 255.0).round() as u8, (color.b() * 255.0).round() as u8)
+}
+
+/// Escapes the characters XML gives a meaning to, which a variable name may well be.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub(crate) fn diagram_to_svg(diagram: &Diagram, redexes: &[StepInfo], normal_form: bool, options: &ExportOptions) -> String {
+    let margin = options.thickness;
+    let width = diagram.rightmost().0 + 2.0 * margin;
+    let height = diagram.bottommost().1 + 2.0 * margin;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"{} {} {width} {height}\">\n", -margin, -margin);
+    if let Some(background) = options.background {
+        svg += &format!("  <rect x=\"{}\" y=\"{}\" width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n", -margin, -margin, hex(background));
+    }
+    for line in &diagram.lines {
+        let (start, end) = match line.direction {
+            Direction::Vertical => ((line.origin.0, line.origin.1 - options.thickness / 2.0), (line.origin.0, line.origin.1 + line.length + options.thickness / 2.0)),
+            Direction::Horizontal => ((line.origin.0, line.origin.1), (line.origin.0 + line.length + options.thickness / 2.0, line.origin.1))
+        };
+        let color = options.color.unwrap_or_else(|| line_color(line, redexes, normal_form));
+        svg += &format!(
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\"/>\n",
+            start.0, start.1, end.0, end.1, hex(color), color.a(), options.thickness
+        );
+    }
+    for arrowhead in &diagram.arrowheads {
+        let color = options.color.unwrap_or_else(|| source_color(&arrowhead.source, redexes, normal_form));
+        for (start, end) in arrowhead.strokes() {
+            svg += &format!(
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-opacity=\"{}\" stroke-width=\"{}\"/>\n",
+                start.0, start.1, end.0, end.1, hex(color), color.a(), options.thickness / 2.0
+            );
+        }
+    }
+    for label in &diagram.labels {
+        let color = options.color.unwrap_or_else(|| source_color(&label.source, redexes, normal_form));
+        svg += &format!(
+            "  <text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{LABEL_SIZE}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\" fill-opacity=\"{}\">{}</text>\n",
+            label.position.0, label.position.1, hex(color), color.a(), escape(&label.text)
+        );
+    }
+    svg + "</svg>\n"
+}
+
+pub(crate) fn export_svg(term: &LambdaExpr, path: &Path, options: &ExportOptions) -> io::Result<()> {
+    let diagram = build_diagram(term, options.style);
+    let redexes = beta_reduce_step(&mut term.clone());
+    fs::write(path, diagram_to_svg(&diagram, &redexes, redexes.is_empty(), options))
+}
+
+pub(crate) fn export_trace_svg(trace: &Trace, dir: &Path, options: &ExportOptions) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (i, term) in trace.terms.iter().enumerate() {
+        let diagram = build_diagram(term, options.style);
+        let redexes = trace.steps.get(i).map(|x| x.as_slice()).unwrap_or(&[]);
+        let svg = diagram_to_svg(&diagram, redexes, i + 1 == trace.terms.len(), options);
+        fs::write(dir.join(format!("{:04}.svg", i)), svg)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use crate::diagrams::{build_diagram, Diagram, DiagramStyle, Direction, PADDING};
+use crate::reduction::Trace;
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+fn cell_char(connections: u8) -> char {
+    match connections {
+        0 => ' ',
+        UP => '╵',
+        DOWN => '╷',
+        LEFT => '╴',
+        RIGHT => '╶',
+        x if x == UP | DOWN => '│',
+        x if x == LEFT | RIGHT => '─',
+        x if x == DOWN | RIGHT => '┌',
+        x if x == DOWN | LEFT => '┐',
+        x if x == UP | RIGHT => '└',
+        x if x == UP | LEFT => '┘',
+        x if x == UP | DOWN | RIGHT => '├',
+        x if x == UP | DOWN | LEFT => '┤',
+        x if x == LEFT | RIGHT | DOWN => '┬',
+        x if x == LEFT | RIGHT | UP => '┴',
+        _ => '┼'
+    }
+}
+
+fn grid(value: f32) -> usize {
+    (value / PADDING).round().max(0.0) as usize
+}
+
+pub(crate) fn diagram_to_text(diagram: &Diagram) -> String {
+    let columns = grid(diagram.rightmost().0) + 1;
+    let rows = grid(diagram.bottommost().1) + 1;
+    let mut cells = vec![vec![0u8; columns]; rows];
+    for line in &diagram.lines {
+        let (column, row) = (grid(line.origin.0), grid(line.origin.1));
+        let length = grid(line.length);
+        match line.direction {
+            Direction::Horizontal => {
+                for i in column..column + length {
+                    cells[row][i] |= RIGHT;
+                    cells[row][i + 1] |= LEFT;
+                }
+            }
+            Direction::Vertical => {
+                for i in row..row + length {
+                    cells[i][column] |= DOWN;
+                    cells[i + 1][column] |= UP;
+                }
+            }
+        }
+    }
+    let mut rendered: Vec<Vec<char>> = cells.iter()
+        .map(|row| row.iter()
+            .flat_map(|connections| [cell_char(*connections), if connections & RIGHT != 0 { '─' } else { ' ' }])
+            .collect())
+        .collect();
+    for arrowhead in &diagram.arrowheads {
+        let (column, row) = (grid(arrowhead.tip.0), grid(arrowhead.tip.1));
+        rendered[row][column * 2] = if arrowhead.leftward { '◀' } else { '▶' };
+    }
+    for label in &diagram.labels {
+        let (column, row) = (grid(label.position.0) * 2, grid(label.position.1));
+        let start = column.saturating_sub(label.text.chars().count() / 2);
+        for (i, c) in label.text.chars().enumerate() {
+            if let Some(cell) = rendered[row].get_mut(start + i) {
+                *cell = c;
+            }
+        }
+    }
+    let lines: Vec<String> = rendered.into_iter()
+        .map(|row| row.into_iter().collect::<String>().trim_end().to_string())
+        .collect();
+    let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|line| !line.is_empty()).map_or(first, |x| x + 1);
+    lines[first..last].iter().map(|line| format!("{line}\n")).collect()
+}
+
+pub(crate) fn trace_to_text(trace: &Trace, style: DiagramStyle) -> String {
+    trace.terms.iter()
+        .enumerate()
+        .map(|(i, term)| {
+            let steps = match i {
+                0 => String::new(),
+                _ => trace.steps[i - 1].iter().map(|step| format!("  {}\n", step.describe())).collect()
+            };
+            format!("step {i}: {term}\n{steps}{}", diagram_to_text(&build_diagram(term, style)))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -0,0 +1,125 @@
+use crate::diagrams::{Arrowhead, Diagram, DiagramStyle, Direction, Label, Line, LineKind, PADDING};
+use crate::expr::{decode_id, ExprType, LambdaExpr};
+
+const NODE_SPACING: f32 = 4.0 * PADDING;
+const LEVEL_HEIGHT: f32 = 4.0 * PADDING;
+const LABEL_GAP: f32 = PADDING;
+
+struct Node<'a> {
+    expr: &'a LambdaExpr,
+    path: Vec<usize>,
+    position: (f32, f32),
+    children: Vec<Node<'a>>,
+}
+
+fn place<'a>(expr: &'a LambdaExpr, path: Vec<usize>, depth: usize, next_leaf: &mut f32) -> Node<'a> {
+    let children: Vec<Node> = expr.children.iter()
+        .enumerate()
+        .map(|(i, child)| {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            place(child, child_path, depth + 1, next_leaf)
+        })
+        .collect();
+    let x = if children.is_empty() {
+        *next_leaf += NODE_SPACING;
+        *next_leaf - NODE_SPACING / 2.0
+    } else {
+        children.iter().map(|child| child.position.0).sum::<f32>() / children.len() as f32
+    };
+    Node {
+        expr,
+        path,
+        position: (x, depth as f32 * LEVEL_HEIGHT + LABEL_GAP),
+        children,
+    }
+}
+
+fn vertical(from: (f32, f32), to_y: f32, node: &Node, kind: LineKind) -> Line {
+    Line {
+        origin: from,
+        length: to_y - from.1,
+        direction: Direction::Vertical,
+        source: node.path.clone(),
+        kind
+    }
+}
+
+fn horizontal(from_x: f32, to_x: f32, y: f32, node: &Node, kind: LineKind) -> Line {
+    Line {
+        origin: (from_x.min(to_x), y),
+        length: (to_x - from_x).abs(),
+        direction: Direction::Horizontal,
+        source: node.path.clone(),
+        kind
+    }
+}
+
+fn label(node: &Node, style: DiagramStyle, binders: &[(usize, (f32, f32))]) -> String {
+    match (node.expr.expr_type.clone(), style) {
+        (ExprType::App, _) => "@".to_string(),
+        (ExprType::Abs, DiagramStyle::Graph) => "λ".to_string(),
+        (ExprType::Abs, _) => format!("λ{}", decode_id(node.expr.id)),
+        (ExprType::Var, DiagramStyle::Graph) => match binders.iter().rev().position(|(id, _)| *id == node.expr.id) {
+            Some(index) => (index + 1).to_string(),
+            None => decode_id(node.expr.id)
+        },
+        (ExprType::Var, _) => decode_id(node.expr.id)
+    }
+}
+
+fn emit(node: &Node, style: DiagramStyle, binders: &mut Vec<(usize, (f32, f32))>, diagram: &mut Diagram) {
+    let (x, y) = node.position;
+    diagram.labels.push(Label {
+        position: node.position,
+        text: label(node, style, binders),
+        source: node.path.clone()
+    });
+    match node.expr.expr_type {
+        ExprType::Var => {
+            let binder = binders.iter().rev().find(|(id, _)| *id == node.expr.id);
+            if let (DiagramStyle::Graph, Some((_, (binder_x, binder_y)))) = (style, binder) {
+                let wire_x = x + LABEL_GAP;
+                diagram.add_line(vertical((wire_x, *binder_y), y, node, LineKind::Binding));
+                let tip_x = if wire_x > *binder_x { binder_x + LABEL_GAP } else { binder_x - LABEL_GAP };
+                diagram.add_line(horizontal(tip_x, wire_x, *binder_y, node, LineKind::Binding));
+                diagram.arrowheads.push(Arrowhead {
+                    tip: (tip_x, *binder_y),
+                    leftward: wire_x > *binder_x,
+                    source: node.path.clone()
+                });
+            }
+        }
+        ExprType::Abs => {
+            let child = &node.children[0];
+            diagram.add_line(vertical((x, y + LABEL_GAP), child.position.1 - LABEL_GAP, node, LineKind::TreeEdge(0)));
+            binders.push((node.expr.id, node.position));
+            emit(child, style, binders, diagram);
+            binders.pop();
+        }
+        ExprType::App => {
+            let bar_y = y + LEVEL_HEIGHT / 2.0;
+            diagram.add_line(vertical((x, y + LABEL_GAP), bar_y, node, LineKind::TreeStem));
+            diagram.add_line(horizontal(node.children[0].position.0, node.children[1].position.0, bar_y, node, LineKind::TreeBar));
+            for (i, child) in node.children.iter().enumerate() {
+                diagram.add_line(vertical((child.position.0, bar_y), child.position.1 - LABEL_GAP, node, LineKind::TreeEdge(i)));
+                emit(child, style, binders, diagram);
+            }
+        }
+    }
+}
+
+fn construct_layered_diagram(expr: &LambdaExpr, style: DiagramStyle) -> Diagram {
+    let root = place(expr, vec![], 0, &mut 0.0);
+    let mut diagram = Diagram::default();
+    emit(&root, style, &mut vec![], &mut diagram);
+    diagram
+}
+
+pub(crate) fn construct_tree_diagram(expr: &LambdaExpr) -> Diagram {
+    construct_layered_diagram(expr, DiagramStyle::Tree)
+}
+
+pub(crate) fn construct_graph_diagram(expr: &LambdaExpr) -> Diagram {
+    construct_layered_diagram(expr, DiagramStyle::Graph)
+}
@@ -0,0 +1,359 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Output, Stdio};
+
+const BINARY: &str = env!("CARGO_BIN_EXE_lambda");
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/translations.txt");
+
+fn run(args: &[&str]) -> Output {
+    Command::new(BINARY).args(args).env("LAMBDA_CACHE", "false").output().unwrap()
+}
+
+fn text_trace(input: &str, backend: &str) -> Output {
+    run(&["text", input, "--trace", "--backend", backend])
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+struct StandIn {
+    child: Child,
+    url: String,
+}
+
+impl StandIn {
+    fn start() -> Self {
+        Self::with_fixture(FIXTURE)
+    }
+
+    fn with_fixture(fixture: &str) -> Self {
+        let mut child = Command::new(BINARY)
+            .args(["serve-mock", fixture, "--port", "0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let url = line.trim().strip_prefix("listening on ").unwrap().to_string();
+        Self { child, url }
+    }
+}
+
+impl Drop for StandIn {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn mock_backend_matches_direct_arithmetic() {
+    let mock = format!("mock:{FIXTURE}");
+    for (query, arithmetic) in [("What is two plus three", "2 + 3"), ("what is  three factorial", "3!"), ("double four then add one", "4 * 2 + 1")] {
+        assert_eq!(stdout(&text_trace(query, &mock)), stdout(&text_trace(arithmetic, &mock)));
+    }
+}
+
+#[test]
+fn mock_backend_reports_unknown_prompts_and_bad_translations() {
+    let mock = format!("mock:{FIXTURE}");
+    let unknown = text_trace("what is nine minus one", &mock);
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("No scripted response"));
+    let unreadable = text_trace("what is the meaning of life", &mock);
+    assert!(!unreadable.status.success());
+    assert!(String::from_utf8_lossy(&unreadable.stderr).contains("Could not read \"forty two\""));
+}
+
+#[test]
+fn ollama_backend_talks_to_stand_in_server() {
+    let server = StandIn::start();
+    let mock = format!("mock:{FIXTURE}");
+    assert_eq!(stdout(&text_trace("what is two plus three", &server.url)), stdout(&text_trace("2 + 3", &mock)));
+    let unknown = text_trace("tell me a joke", &server.url);
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("No scripted response"));
+}
+
+#[test]
+fn animation_export_runs_offline() {
+    let path = std::env::temp_dir().join(format!("lambda-pipeline-{}.gif", std::process::id()));
+    let output = run(&["animate", "what is two plus three", path.to_str().unwrap(), "--backend", &format!("mock:{FIXTURE}"), "--size", "320x180"]);
+    stdout(&output);
+    let steps = stdout(&text_trace("2 + 3", &format!("mock:{FIXTURE}"))).lines().filter(|x| x.starts_with("step ")).count();
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (320, 180));
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        let lit = |columns: std::ops::Range<usize>| frame.buffer.chunks(4).enumerate()
+            .filter(|(i, pixel)| columns.contains(&(i % 320)) && pixel[..3] != [0, 0, 0])
+            .count();
+        assert!(lit(0..160) > 0 && lit(160..320) > 0, "frame {frames} is missing the diagram or the text");
+        frames += 1;
+    }
+    assert_eq!(frames, steps + " = 5".len());
+    let _ = std::fs::remove_file(path);
+}
+
+fn gif_frames(path: &std::path::Path) -> Vec<Vec<u8>> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(std::fs::File::open(path).unwrap()).unwrap();
+    let mut frames = vec![];
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push(frame.buffer.to_vec());
+    }
+    frames
+}
+
+#[test]
+fn animations_end_on_the_normal_form() {
+    let dir = std::env::temp_dir().join(format!("lambda-normal-form-{}", std::process::id()));
+    let (normal, one_step) = (dir.join("normal.gif"), dir.join("one-step.gif"));
+    std::fs::create_dir_all(&dir).unwrap();
+    stdout(&run(&["animate", "\\y.y", normal.to_str().unwrap(), "--size", "320x180"]));
+    assert_eq!(gif_frames(&normal).len(), 1);
+    stdout(&run(&["animate", "(\\x.x)(\\y.y)", one_step.to_str().unwrap(), "--size", "320x180"]));
+    let frames = gif_frames(&one_step);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1], gif_frames(&normal)[0]);
+    let sequence = dir.join("one-step");
+    stdout(&run(&["animate", "(\\x.x)(\\y.y)", sequence.to_str().unwrap(), "--size", "320x180"]));
+    let mut files = std::fs::read_dir(&sequence).unwrap().map(|x| x.unwrap().path()).collect::<Vec<_>>();
+    files.sort();
+    assert_ne!(std::fs::read(&files[0]).unwrap(), std::fs::read(files.last().unwrap()).unwrap());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn missing_model_lists_installed_models() {
+    let server = StandIn::start();
+    let output = run(&["text", "what is two plus three", "--backend", &server.url, "--model", "mistral", "--english", "off"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Model mistral is not available"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("it has llama3:latest"));
+    let fallback = run(&["text", "what is two plus three", "--backend", &server.url, "--model", "mistral"]);
+    assert!(String::from_utf8_lossy(&fallback.stderr).contains("Model mistral is not available"));
+    assert_eq!(stdout(&fallback), stdout(&run(&["text", "2 + 3"])));
+}
+
+#[test]
+fn malformed_translations_are_retried_with_the_parse_error() {
+    let mock = format!("mock:{FIXTURE}");
+    let server = StandIn::start();
+    for backend in [mock.as_str(), server.url.as_str()] {
+        assert_eq!(stdout(&text_trace("what is six times seven", backend)), stdout(&text_trace("6 * 7", &mock)));
+    }
+    let without_retries = run(&["text", "what is six times seven", "--backend", &mock, "--retries", "0"]);
+    assert!(String::from_utf8_lossy(&without_retries.stderr).contains("gave up after 1 attempt"));
+    let unreadable = text_trace("what is the meaning of life", &mock);
+    assert!(String::from_utf8_lossy(&unreadable.stderr).contains("gave up after 3 attempts"));
+}
+
+#[test]
+fn structured_translations_keep_their_nesting() {
+    let mock = format!("mock:{FIXTURE}");
+    let server = StandIn::start();
+    for backend in [mock.as_str(), server.url.as_str()] {
+        assert_eq!(stdout(&text_trace("what is three plus one all times two", backend)), stdout(&text_trace("(3 + 1) * 2", &mock)));
+        assert_eq!(stdout(&text_trace("what is the factorial of three", backend)), stdout(&text_trace("3!", &mock)));
+    }
+    let output = run(&["text", "what is the factorial of three", "--backend", &mock, "--retries", "0"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid expression tree: ! takes one arg, got 0"));
+}
+
+fn lambda_trace(input: &str, backend: &str) -> Output {
+    run(&["text", input, "--trace", "--lambda", "--backend", backend])
+}
+
+fn result_line(output: &Output) -> String {
+    stdout(output).lines().last().unwrap().to_string()
+}
+
+#[test]
+fn lambda_answers_use_the_prelude_and_read_back() {
+    let mock = format!("mock:{FIXTURE}");
+    assert_eq!(stdout(&lambda_trace("apply the identity function to K", &mock)).lines().next(), Some("step 0: (λx.x)(λx.λy.x)"));
+    for (query, result) in [("apply the identity function to K", "result = true"), ("is zero zero", "result = true"), ("pair up one and two", "result = (1, 2)"), ("list one and two", "result = [1, 2]")] {
+        assert_eq!(result_line(&lambda_trace(query, &mock)), result);
+    }
+    assert!(!stdout(&lambda_trace("\\x. add x 1", &mock)).contains("result"));
+    assert!(stdout(&run(&["text", "\\i.\\f.if"])).starts_with("╶"));
+    let unknown = lambda_trace("what is the factorial of nothing", &mock);
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("Unknown name fact"));
+}
+
+fn repl(lines: &str, extra: &[&str]) -> String {
+    let mut child = Command::new(BINARY)
+        .args(["repl", "--backend", &format!("mock:{FIXTURE}")])
+        .args(extra)
+        .env("LAMBDA_CACHE", "false")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(lines.as_bytes()).unwrap();
+    stdout(&child.wait_with_output().unwrap())
+}
+
+#[test]
+fn follow_ups_refer_to_earlier_results() {
+    let output = repl("what is two times three\nnow multiply that by two\n$1 + ans\n$7\n", &[]);
+    assert_eq!(output.lines().collect::<Vec<&str>>(), [
+        "$1 = 6",
+        "$2 = 12",
+        "$3 = 18",
+        "error: $7 refers to an earlier result, but there are only $1 to $3"
+    ]);
+    let output = repl("pair up one and two\ntake the first of that\nnow multiply that by two\n", &["--lambda"]);
+    assert_eq!(output.lines().take(2).collect::<Vec<&str>>(), ["$1 = (1, 2)", "$2 = 1"]);
+    assert!(output.lines().nth(2).unwrap().starts_with("error: Could not read \"ans * 2\""));
+}
+
+#[test]
+fn english_parser_answers_simple_queries_offline() {
+    let text = |query: &str, extra: &[&str]| run(&[&["text", query, "--trace", "--backend", "http://127.0.0.1:9", "--timeout", "5"], extra].concat());
+    for (query, result) in [("what is twenty-three plus the square of four", "result = 39"), ("the sum of two and three times four", "result = 14"), ("the factorial of three", "result = 6")] {
+        assert_eq!(result_line(&text(query, &[])), result);
+    }
+    assert_eq!(result_line(&text("what is two plus three", &["--english", "first"])), "result = 5");
+    assert!(!text("what is two plus three", &["--english", "off"]).status.success());
+    let offline = ["--backend", "http://127.0.0.1:9", "--timeout", "5"];
+    let words = repl("six\nthat squared plus $1\ntwo three\nfour-two\n", &offline);
+    let lines = words.lines().collect::<Vec<&str>>();
+    assert_eq!(lines[..2], ["$1 = 6", "$2 = 42"]);
+    assert!(lines[2].starts_with("error: ") && lines[2].ends_with("Unexpected \"three\""));
+    assert!(lines[3].starts_with("error: ") && lines[3].ends_with("Unexpected \"two\""));
+}
+
+#[test]
+fn recorded_translations_replay_without_a_server() {
+    let cache = std::env::temp_dir().join(format!("lambda-recorded-{}.json", std::process::id()));
+    let query = |backend: &str| run(&["text", "double four then add one", "--trace", "--backend", backend, "--cache-file", cache.to_str().unwrap(), "--english", "off"]);
+    let server = StandIn::start();
+    let url = server.url.clone();
+    assert_eq!(result_line(&query(&url)), "result = 9");
+    drop(server);
+    let replayed = query(&url);
+    assert_eq!(result_line(&replayed), "result = 9");
+    assert!(String::from_utf8_lossy(&replayed.stderr).contains("only recorded translations can be replayed"));
+    let _ = std::fs::remove_file(cache);
+}
+
+#[test]
+fn cached_translations_are_replayed_until_cleared() {
+    let dir = std::env::temp_dir().join(format!("lambda-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (cache, fixture) = (dir.join("translations.json"), dir.join("other.txt"));
+    std::fs::write(&fixture, "what is two plus three => 4 + 5\n").unwrap();
+    let (first, other) = (StandIn::start(), StandIn::with_fixture(fixture.to_str().unwrap()));
+    let query = |server: &StandIn, extra: &[&str]| result_line(&run(&[&["text", "what is two plus three", "--trace", "--backend", &server.url], extra].concat()));
+    let cache_file = ["--cache-file", cache.to_str().unwrap()];
+    assert_eq!(query(&first, &cache_file), "result = 5");
+    assert!(std::fs::read_to_string(&cache).unwrap().contains("\"answer\": \"2 + 3\""));
+    assert_eq!(query(&other, &cache_file), "result = 5");
+    assert_eq!(query(&other, &[]), "result = 9");
+    assert_eq!(query(&other, &[&cache_file[..], &["--clear-cache"]].concat()), "result = 9");
+    assert_eq!(query(&first, &cache_file), "result = 9");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn terms_with_free_variables_are_rejected() {
+    let output = run(&["text", "\\x.y"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Free variable y"));
+    assert_eq!(repl("\\x.y\n", &[]).trim(), "error: Free variable y");
+}
+
+fn lit_pixels(path: &std::path::Path) -> usize {
+    let mut reader = png::Decoder::new(std::fs::File::open(path).unwrap()).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels[..info.buffer_size()].chunks(4).filter(|pixel| pixel[..3] != [0, 0, 0]).count()
+}
+
+#[test]
+fn png_export_follows_the_line_thickness() {
+    let dir = std::env::temp_dir().join(format!("lambda-thickness-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (thin, thick) = (dir.join("thin.png"), dir.join("thick.png"));
+    stdout(&run(&["png", "\\x.x x", thin.to_str().unwrap(), "--size", "200x200", "--thickness", "1"]));
+    stdout(&run(&["png", "\\x.x x", thick.to_str().unwrap(), "--size", "200x200", "--thickness", "10"]));
+    assert!(lit_pixels(&thick) > 3 * lit_pixels(&thin));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn eta_command_normalizes_and_expands() {
+    assert_eq!(stdout(&run(&["eta", "\\y.\\x.y x"])).trim(), "λy.y");
+    assert_eq!(stdout(&run(&["eta", "\\f.f", "--eta", "long:2"])).trim(), "λf.λv.(f)(v)");
+}
+
+#[test]
+fn svg_labels_escape_markup() {
+    let path = std::env::temp_dir().join(format!("lambda-labels-{}.svg", std::process::id()));
+    stdout(&run(&["svg", "\\&.\\<.&<", path.to_str().unwrap(), "--style", "tree"]));
+    let svg = std::fs::read_to_string(&path).unwrap();
+    assert!(svg.contains(">λ&amp;</text>") && svg.contains(">λ&lt;</text>"));
+    assert!(!svg.contains("&<"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn bad_flags_and_config_files_exit_cleanly() {
+    let config = std::env::temp_dir().join(format!("lambda-config-{}.toml", std::process::id()));
+    std::fs::write(&config, "port = \"eleven\"\n").unwrap();
+    let prompt = std::env::temp_dir().join(format!("lambda-missing-prompt-{}.txt", std::process::id()));
+    for (args, message) in [
+        (vec!["text", "2 + 3", "--workers", "none"], "--workers expects a positive integer"),
+        (vec!["text", "2 + 3", "--config", config.to_str().unwrap()], "Invalid config"),
+        (vec!["text", "2 + 3", "--system-prompt", prompt.to_str().unwrap()], "Could not read"),
+        (vec!["frobnicate"], "Unknown command frobnicate")
+    ] {
+        let output = run(&args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{stderr}");
+        assert!(stderr.contains(message) && !stderr.contains("panicked"), "{stderr}");
+    }
+    let unreadable = run(&["text", "2 +"]);
+    assert_eq!(unreadable.status.code(), Some(1));
+    assert!(!String::from_utf8_lossy(&unreadable.stderr).contains("panicked"));
+    let _ = std::fs::remove_file(config);
+}
+
+#[test]
+fn text_diagrams_match_their_snapshots() {
+    let text = |style: &str| stdout(&run(&["text", "\\x.x x", "--style", style]));
+    assert_eq!(text("tromp"), [
+        "╶─┬───┬─╴\n",
+        "  │   │\n",
+        "  ├───┘\n",
+        "  ╵\n",
+    ].concat());
+    assert_eq!(text("tree"), [
+        "       λx\n",
+        "        ╷\n",
+        "        │\n",
+        "        ╵\n",
+        "        @\n",
+        "        ╷\n",
+        "    ┌───┴───┐\n",
+        "    ╵       ╵\n",
+        "    x       x\n",
+    ].concat());
+    assert_eq!(text("graph"), [
+        "      ▶ λ ◀───┐\n",
+        "      │ ╷     │\n",
+        "      │ │     │\n",
+        "      │ ╵     │\n",
+        "      │ @     │\n",
+        "      │ ╷     │\n",
+        "    ┌─┼─┴───┐ │\n",
+        "    ╵ │     ╵ │\n",
+        "    1 ╵     1 ╵\n",
+    ].concat());
+}